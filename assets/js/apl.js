@@ -54,23 +54,41 @@ export default {
         SIM_REACTION_TIME: "SimReactionTime",
         SIM_TARGET_LEVEL: "SimTargetLevel",
     },
+    target_type: {
+        NONE: "None",
+        CURRENT: "Current",
+        LOWEST_AURA_STACKS: "LowestAuraStacks",
+        MISSING_AURA: "MissingAura",
+        HIGHEST_HEALTH: "HighestHealth",
+        CYCLE: "Cycle",
+        NEWEST: "Newest",
+    },
 
     actions() {
         return [
             { key: "None", title: "Do nothing" },
             { key: "Sequence", title: "Sequence" },
-            { key: "ArcaneMissiles", title: "Cast: Arcane Missiles" },
+            { key: "CallList", title: "Call list" },
+            { key: "RunList", title: "Run list" },
+            { key: "ArcaneExplosion", title: "Cast: Arcane Explosion" },
+            { key: "ArcaneMissiles", title: "Cast: Arcane Missiles", targeted: true, ranks: 8 },
             { key: "ArcanePower", title: "Cast: Arcane Power", talent: "arcane_power" },
             { key: "Berserking", title: "Cast: Berserking", race: "Troll" },
+            { key: "BlastWave", title: "Cast: Blast Wave", talent: "blast_wave" },
             { key: "ColdSnap", title: "Cast: Cold Snap", talent: "cold_snap" },
             { key: "Combustion", title: "Cast: Combustion", talent: "combustion" },
+            { key: "ConeOfCold", title: "Cast: Cone of Cold" },
             { key: "Evocation", title: "Cast: Evocation" },
-            { key: "Fireball", title: "Cast: Fireball" },
-            { key: "FireBlast", title: "Cast: Fire Blast" },
-            { key: "Frostbolt", title: "Cast: Frostbolt" },
+            { key: "Fireball", title: "Cast: Fireball", targeted: true, ranks: 12 },
+            { key: "FireBlast", title: "Cast: Fire Blast", targeted: true, ranks: 7 },
+            { key: "Flamestrike", title: "Cast: Flamestrike" },
+            { key: "Frostbolt", title: "Cast: Frostbolt", targeted: true, ranks: 11 },
+            { key: "FrostNova", title: "Cast: Frost Nova" },
+            { key: "IceBarrier", title: "Cast: Ice Barrier", talent: "ice_barrier" },
+            { key: "IceBlock", title: "Cast: Ice Block", talent: "ice_block" },
             { key: "PresenceOfMind", title: "Cast: Presence of Mind", talent: "presence_of_mind" },
-            { key: "Pyroblast", title: "Cast: Pyroblast", talent: "pyroblast" },
-            { key: "Scorch", title: "Cast: Scorch" },
+            { key: "Pyroblast", title: "Cast: Pyroblast", talent: "pyroblast", targeted: true, ranks: 8 },
+            { key: "Scorch", title: "Cast: Scorch", targeted: true, ranks: 7 },
             { key: "ManaGem", title: "Use: Mana Gem" },
             { key: "ManaPotion", title: "Use: Mana Potion" },
             { key: "CelestialOrb", title: "off_hand", item: items.ids.CELESTIAL_ORB },
@@ -86,9 +104,6 @@ export default {
             { key: "EphemeralPower", title: "trinket", item: items.ids.TRINKET_TOEP },
             { key: "ManaInfusion", title: "trinket", item: items.ids.TRINKET_WARMTH_OF_FORGIVENESS },
             { key: "UnstablePower", title: "trinket", item: items.ids.TRINKET_ZHC },
-            { key: "Innervate", title: "External: Innervate" },
-            { key: "ManaTide", title: "External: Mana Tide", faction: "horde" },
-            { key: "PowerInfusion", title: "External: Power Infusion" },
         ];
     },
 
@@ -96,7 +111,15 @@ export default {
         return {
             id: common.uuid(),
             type: "apl",
-            version: "1.0",
+            version: "1.2",
+            name: "",
+            items: [],
+            lists: [],
+        };
+    },
+    list() {
+        return {
+            id: common.uuid(),
             name: "",
             items: [],
         };
@@ -123,7 +146,16 @@ export default {
             id: common.uuid(),
             key: "None",
             target_id: 1,
+            target: this.target(),
             sequence: [],
+            list: "",
+            rank: 0,
+        }
+    },
+    target() {
+        return {
+            target_type: this.target_type.NONE,
+            vint: 0,
         }
     },
     value() {
@@ -151,4 +183,48 @@ export default {
     isPreset(id) {
         return id.indexOf("preset") === 0;
     },
+
+    // Updates apls saved by older versions
+    migrate(data) {
+        let version = this.apl().version;
+        if (data.version == version || (data.id && this.isPreset(data.id)))
+            return data;
+        let from = parseFloat(data.version) || 1.0;
+
+        // 1.1: Cold Snap cooldowns used the id 11958, which is Ice Block
+        if (from < 1.1) {
+            let cooldownTypes = [
+                this.value_type.PLAYER_COOLDOWN_EXISTS,
+                this.value_type.PLAYER_COOLDOWN_REACT,
+                this.value_type.PLAYER_COOLDOWN_DURATION,
+            ];
+            let migrateCondition = (cond) => {
+                for (let value of cond.values || []) {
+                    if (cooldownTypes.includes(value.value_type) && value.vint == 11958)
+                        value.vint = common.cooldowns.COLD_SNAP;
+                }
+                (cond.conditions || []).forEach(migrateCondition);
+            };
+            let items = (data.items || []).concat(...(data.lists || []).map(l => l.items || []));
+            items.forEach(item => migrateCondition(item.condition || {}));
+        }
+
+        // 1.2: Innervate, Mana Tide and Power Infusion are cast by support units
+        if (from < 1.2) {
+            let external = ["Innervate", "ManaTide", "PowerInfusion"];
+            let keep = (item) => {
+                if (!item.action)
+                    return true;
+                if (item.action.sequence)
+                    item.action.sequence = item.action.sequence.filter(a => !external.includes(a.key));
+                return !external.includes(item.action.key);
+            };
+            data.items = (data.items || []).filter(keep);
+            for (let list of data.lists || [])
+                list.items = (list.items || []).filter(keep);
+        }
+
+        data.version = version;
+        return data;
+    },
 }
\ No newline at end of file
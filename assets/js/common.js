@@ -74,6 +74,7 @@ export default {
         COMBUSTION: 29977,
         EVOCATION: 12051,
         FIRE_VULNERABILITY: 22959,
+        IGNITE: 12848,
         INNERVATE: 29166,
         PRESENCE_OF_MIND: 12043,
         POWER_INFUSION: 10060,
@@ -95,7 +96,7 @@ export default {
     cooldowns: {
         ARCANE_POWER: 12042,
         BERSERKING: 20554,
-        COLD_SNAP: 11958,
+        COLD_SNAP: 12472,
         COMBUSTION: 29977,
         EVOCATION: 12051,
         FIRE_BLAST: 10199,
@@ -90,7 +90,6 @@ const defaultApls = () => {
         apl.getAction("Scorch"),
         apl.getAction("Scorch"),
         apl.getAction("Frostbolt"),
-        apl.getAction("PowerInfusion"),
         ...cds.slice(1)
     ];
     fire.items.push(item);
@@ -138,7 +137,6 @@ const defaultApls = () => {
     item.action = apl.getAction("Sequence");
     item.action.sequence = [
         apl.getAction("Frostbolt"),
-        apl.getAction("PowerInfusion"),
         ...cds
     ];
     frost.items.push(item);
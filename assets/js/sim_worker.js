@@ -17,6 +17,10 @@ onmessage = (event) => {
         })
         .catch(e => {
             console.warn(e);
+            postMessage({
+                type: "error",
+                error: e,
+            });
         });
     }
 }
\ No newline at end of file
@@ -3,7 +3,7 @@ use crate::aura;
 use crate::spell;
 use serde::{Serialize, Deserialize};
 
-#[derive(Default, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[derive(Default, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
 pub enum AplConditionType {
     #[default]
     None,
@@ -15,7 +15,7 @@ pub enum AplConditionType {
     True,
 }
 
-#[derive(Default, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[derive(Default, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
 pub enum AplConditionOp {
     #[default]
     None,
@@ -27,7 +27,7 @@ pub enum AplConditionOp {
     Lte,
 }
 
-#[derive(Default, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[derive(Default, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
 pub enum AplValueType {
     #[default]
     None,
@@ -60,26 +60,36 @@ pub enum AplValueType {
     SimTargetLevel,
 }
 
-#[derive(Default, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[derive(Default, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
 pub enum AplActionKey {
     #[default]
     None,
+    ArcaneExplosion,
     ArcaneMissiles,
     ArcanePotency,
     ArcanePower,
     Berserking,
+    BlastWave,
     BurstOfKnowledge,
+    CallList,
     CelestialOrb,
     ChaosFire,
     ChromaticInfusion,
     ColdSnap,
     Combustion,
+    ConeOfCold,
     EphemeralPower,
     EssenceOfSapphiron,
     Evocation,
     Fireball,
     FireBlast,
+    Flamestrike,
     Frostbolt,
+    FrostNova,
+    IceBarrier,
+    IceBlock,
+    // Innervate, ManaTide and PowerInfusion are cast by support units
+    // They are kept so saved apls still load and run, players skip them
     Innervate,
     ManaGem,
     ManaInfusion,
@@ -92,15 +102,37 @@ pub enum AplActionKey {
     PresenceOfMind,
     Pyroblast,
     RobeArchmage,
+    RunList,
     Scorch,
     Sequence,
     UnstablePower,
     Wait,
 }
 
+#[derive(Default, Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub enum AplTargetType {
+    #[default]
+    None,
+    Current,
+    LowestAuraStacks,
+    MissingAura,
+    HighestHealth,
+    Cycle,
+    Newest,
+}
+
 #[derive(Default, Serialize, Deserialize, Clone)]
 pub struct Apl {
     pub items: Vec<AplItem>,
+    #[serde(default)]
+    pub lists: Vec<AplList>,
+}
+
+// A named list of items that can be used with CallList and RunList actions
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct AplList {
+    pub name: String,
+    pub items: Vec<AplItem>,
 }
 
 #[derive(Default, Serialize, Deserialize, Clone)]
@@ -121,7 +153,21 @@ pub struct AplCondition {
 pub struct AplAction {
     pub key: AplActionKey,
     pub target_id: i32,
+    #[serde(default)]
+    pub target: AplTarget,
     pub sequence: Vec<AplAction>,
+    #[serde(default)]
+    pub list: String,
+    // Spell rank, 0 is the highest rank
+    #[serde(default)]
+    pub rank: i32,
+}
+
+// Picks the target of an action, falls back to target_id when target_type is None
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct AplTarget {
+    pub target_type: AplTargetType,
+    pub vint: i32,
 }
 
 #[derive(Default, Serialize, Deserialize, Clone)]
@@ -1,3 +1,4 @@
+use crate::common::School;
 use crate::stats::Stats;
 use std::collections::HashMap;
 
@@ -7,11 +8,20 @@ pub const CLEARCAST: i32 = 12536;
 pub const COMBUSTION: i32 = 29977;
 pub const EVOCATION: i32 = 12051;
 pub const FIRE_VULNERABILITY: i32 = 22959;
+pub const FROSTBITE: i32 = 12494;
+pub const FROST_NOVA: i32 = 10230;
 pub const INNERVATE: i32 = 29166;
 pub const MANA_TIDE: i32 = 17359;
 pub const PRESENCE_OF_MIND: i32 = 12043;
 pub const POWER_INFUSION: i32 = 10060;
 pub const WINTERS_CHILL: i32 = 12579;
+// Raid debuffs
+pub const CURSE_OF_ELEMENTS: i32 = 11722;
+pub const CURSE_OF_SHADOWS: i32 = 17937;
+pub const JUDGEMENT_OF_WISDOM: i32 = 20355;
+pub const SHADOW_VULNERABILITY: i32 = 17800;
+pub const SPELL_VULNERABILITY: i32 = 23605;
+pub const STORMSTRIKE: i32 = 17364;
 // Trinkets
 pub const ARCANE_POTENCY: i32 = 24544;
 pub const BLUE_DRAGON: i32 = 23688;
@@ -42,6 +52,10 @@ pub struct Aura {
     pub owner_id: i32,
     pub is_shared: bool,
     pub is_hidden: bool,
+    // Auras of items and racials have no school
+    pub school: School,
+    // Strength of effects that depend on the caster, ie. Improved Shadow Bolt points
+    pub value: f64,
     pub t_gained: f64,
     pub t_refreshed: f64,
     pub t_expires: f64,
@@ -143,6 +157,10 @@ impl Auras {
         self.get_aura(id, owner_id).is_some()
     }
 
+    pub fn has_own(&self, id: i32, owner_id: i32) -> bool {
+        self.auras.iter().any(|aura| aura.id == id && aura.owner_id == owner_id)
+    }
+
     pub fn has_any(&self, id: i32) -> bool {
         self.auras.iter().any(|aura| aura.id == id)
     }
@@ -166,7 +184,9 @@ pub fn arcane_potency() -> Aura {
 }
 
 pub fn arcane_power() -> Aura {
-    Aura::new(ARCANE_POWER, String::from("Arcane Power"), 15.0)
+    let mut aura = Aura::new(ARCANE_POWER, String::from("Arcane Power"), 15.0);
+    aura.school = School::Arcane;
+    aura
 }
 
 pub fn berserking() -> Aura {
@@ -192,15 +212,30 @@ pub fn chromatic_infusion() -> Aura {
 }
 
 pub fn clearcast() -> Aura {
-    Aura::new(CLEARCAST, String::from("Clearcast"), 15.0)
+    let mut aura = Aura::new(CLEARCAST, String::from("Clearcast"), 15.0);
+    aura.school = School::Arcane;
+    aura
 }
 
 pub fn combustion() -> Aura {
     let mut aura = Aura::new(COMBUSTION, String::from("Combustion"), 1000.0);
+    aura.school = School::Fire;
     aura.max_stacks = 20;
     aura
 }
 
+pub fn curse_of_elements() -> Aura {
+    let mut aura = Aura::new(CURSE_OF_ELEMENTS, String::from("Curse of the Elements"), 300.0);
+    aura.school = School::Shadow;
+    aura
+}
+
+pub fn curse_of_shadows() -> Aura {
+    let mut aura = Aura::new(CURSE_OF_SHADOWS, String::from("Curse of Shadow"), 300.0);
+    aura.school = School::Shadow;
+    aura
+}
+
 pub fn enigmas_answer() -> Aura {
     let mut aura = Aura::new(ENIGMAS_ANSWER, String::from("Enigma's Answer"), 20.0);
     aura.max_stacks = 4;
@@ -221,21 +256,46 @@ pub fn essence_of_sapphiron() -> Aura {
 }
 
 pub fn evocation() -> Aura {
-    Aura::new(EVOCATION, String::from("Evocation"), 8.0)
+    let mut aura = Aura::new(EVOCATION, String::from("Evocation"), 8.0);
+    aura.school = School::Arcane;
+    aura
 }
 
 pub fn fire_vulnerability() -> Aura {
     let mut aura = Aura::new(FIRE_VULNERABILITY, String::from("Fire Vulnerability"), 30.0);
+    aura.school = School::Fire;
     aura.max_stacks = 5;
     aura
 }
 
+pub fn frostbite() -> Aura {
+    let mut aura = Aura::new(FROSTBITE, String::from("Frostbite"), 5.0);
+    aura.school = School::Frost;
+    aura
+}
+
+pub fn frost_nova() -> Aura {
+    let mut aura = Aura::new(FROST_NOVA, String::from("Frost Nova"), 8.0);
+    aura.school = School::Frost;
+    aura
+}
+
 pub fn innervate() -> Aura {
-    Aura::new(INNERVATE, String::from("Innervate"), 20.0)
+    let mut aura = Aura::new(INNERVATE, String::from("Innervate"), 20.0);
+    aura.school = School::Nature;
+    aura
+}
+
+pub fn judgement_of_wisdom() -> Aura {
+    let mut aura = Aura::new(JUDGEMENT_OF_WISDOM, String::from("Judgement of Wisdom"), 10.0);
+    aura.school = School::Holy;
+    aura
 }
 
 pub fn mana_tide() -> Aura {
-    Aura::new(MANA_TIDE, String::from("Mana Tide"), 12.0)
+    let mut aura = Aura::new(MANA_TIDE, String::from("Mana Tide"), 12.0);
+    aura.school = School::Nature;
+    aura
 }
 
 pub fn mind_quickening() -> Aura {
@@ -260,11 +320,36 @@ pub fn obsidian_insight() -> Aura {
 }
 
 pub fn power_infusion() -> Aura {
-    Aura::new(POWER_INFUSION, String::from("Power Infusion"), 15.0)
+    let mut aura = Aura::new(POWER_INFUSION, String::from("Power Infusion"), 15.0);
+    aura.school = School::Holy;
+    aura
 }
 
 pub fn presence_of_mind() -> Aura {
-    Aura::new(PRESENCE_OF_MIND, String::from("Presence of Mind"), 1000.0)
+    let mut aura = Aura::new(PRESENCE_OF_MIND, String::from("Presence of Mind"), 1000.0);
+    aura.school = School::Arcane;
+    aura
+}
+
+// Improved Shadow Bolt, 4% shadow damage per point for the next 4 hits
+// The stacks are the charges that are left
+pub fn shadow_vulnerability(points: u8) -> Aura {
+    let mut aura = Aura::new(SHADOW_VULNERABILITY, String::from("Shadow Vulnerability"), 12.0);
+    aura.school = School::Shadow;
+    aura.max_stacks = 4;
+    aura.stack_increment = 4;
+    aura.value = 0.04 * points as f64;
+    aura
+}
+
+pub fn spell_vulnerability() -> Aura {
+    Aura::new(SPELL_VULNERABILITY, String::from("Spell Vulnerability"), 5.0)
+}
+
+pub fn stormstrike() -> Aura {
+    let mut aura = Aura::new(STORMSTRIKE, String::from("Stormstrike"), 12.0);
+    aura.school = School::Nature;
+    aura
 }
 
 pub fn unstable_power() -> Aura {
@@ -278,6 +363,7 @@ pub fn unstable_power() -> Aura {
 
 pub fn winters_chill() -> Aura {
     let mut aura = Aura::new(WINTERS_CHILL, String::from("Winter's Chill"), 15.0);
+    aura.school = School::Frost;
     aura.max_stacks = 5;
     aura
 }
\ No newline at end of file
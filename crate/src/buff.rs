@@ -0,0 +1,369 @@
+use crate::common::Race;
+use crate::config::PlayerConfig;
+use crate::stats::Stats;
+use serde::{Serialize, Deserialize};
+
+// Only one buff from each group can be active, buffs in group None always stack
+#[derive(Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BuffGroup {
+    #[default]
+    None,
+    ArcaneElixir,
+    BlessingOfWisdom,
+    FireElixir,
+    Flask,
+    Food,
+    ManaSpring,
+    MarkOfTheWild,
+    WeaponOil,
+}
+
+#[derive(Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Faction {
+    #[default]
+    Any,
+    Alliance,
+    Horde,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Buff {
+    // Raid buffs
+    ArcaneIntellect,
+    BlessingOfKings,
+    BlessingOfWisdom,
+    ImpBlessingOfWisdom,
+    DivineSpirit,
+    ImpManaSpring,
+    ManaSpring,
+    MarkOfTheWild,
+    ImpMarkOfTheWild,
+    MoonkinAura,
+    // Flasks
+    FlaskOfDistilledWisdom,
+    FlaskOfSupremePower,
+    // Elixirs
+    ArcaneElixir,
+    GreaterArcaneElixir,
+    ElixirOfFirepower,
+    ElixirOfGreaterFirepower,
+    ElixirOfFrostPower,
+    // Food
+    NightfinSoup,
+    RunnTumTuberSurprise,
+    WellFed,
+    // Weapon oils
+    BlessedWizardOil,
+    BrilliantManaOil,
+    BrilliantWizardOil,
+    WizardOil,
+    // World buffs
+    DarkmoonFaireDmg,
+    RallyingCry,
+    SlipkiksSavvy,
+    Songflower,
+    SoulRevival,
+    SpiritOfZandalar,
+    TracesOfSilithyst,
+    WarchiefsBlessing,
+}
+
+// What a buff does
+#[derive(Clone)]
+pub struct BuffEffect {
+    pub group: BuffGroup,
+    pub faction: Faction,
+    pub stats: Stats,
+    // Multiplies intellect and spirit
+    pub stat_multiplier: f64,
+    pub dmg_multiplier: f64,
+}
+
+impl BuffEffect {
+    pub fn new(group: BuffGroup) -> Self {
+        Self {
+            group,
+            faction: Faction::Any,
+            stats: Stats::default(),
+            stat_multiplier: 1.0,
+            dmg_multiplier: 1.0,
+        }
+    }
+}
+
+// The buffs that are active for a player of this race
+// The first buff of each group wins and buffs from the other faction are ignored
+pub fn active_buffs(buffs: &[Buff], race: Race) -> Vec<Buff> {
+    let mut active: Vec<Buff> = vec![];
+
+    for buff in buffs {
+        let e = effect(*buff);
+
+        if e.faction == Faction::Alliance && race.is_horde() || e.faction == Faction::Horde && !race.is_horde() {
+            continue;
+        }
+        if active.contains(buff) || e.group != BuffGroup::None && active.iter().any(|b| effect(*b).group == e.group) {
+            continue;
+        }
+
+        active.push(*buff);
+    }
+
+    active
+}
+
+// Gear stats with the class base stats and the active buffs, like the character sheet
+// Players that enter their character sheet stats turn gear_stats off and get them as they are
+pub fn player_stats(config: &PlayerConfig, buffs: &[Buff], base: Stats, int_per_crit: f64) -> Stats {
+    let mut stats = config.stats;
+
+    if !config.gear_stats {
+        return stats;
+    }
+
+    stats+= base;
+
+    for b in buffs.iter() {
+        stats+= effect(*b).stats;
+    }
+
+    // Kings and Zandalar multiply everything else
+    for b in buffs.iter() {
+        let multiplier = effect(*b).stat_multiplier;
+        stats.int*= multiplier;
+        stats.spi*= multiplier;
+    }
+
+    // Expansive Mind
+    if config.race == Race::Gnome {
+        stats.int*= 1.05;
+    }
+    // The Human Spirit
+    if config.race == Race::Human {
+        stats.spi*= 1.05;
+    }
+
+    stats.int = stats.int.round();
+    stats.spi = stats.spi.round();
+    stats.crit+= stats.int / int_per_crit;
+
+    stats
+}
+
+// Damage multiplier from the active buffs and the old buff flags
+pub fn dmg_multiplier(config: &PlayerConfig, buffs: &[Buff]) -> f64 {
+    let mut dmg = 1.0;
+
+    if config.dmf_dmg && !buffs.contains(&Buff::DarkmoonFaireDmg) {
+        dmg*= 1.1;
+    }
+    if config.soul_revival && !buffs.contains(&Buff::SoulRevival) {
+        dmg*= 1.1;
+    }
+    if config.traces_of_silithyst && !buffs.contains(&Buff::TracesOfSilithyst) {
+        dmg*= 1.05;
+    }
+    for b in buffs {
+        dmg*= effect(*b).dmg_multiplier;
+    }
+
+    dmg
+}
+
+pub fn effect(buff: Buff) -> BuffEffect {
+    match buff {
+        Buff::ArcaneIntellect => {
+            let mut e = BuffEffect::new(BuffGroup::None);
+            e.stats.int = 31.0;
+            e
+        }
+        Buff::BlessingOfKings => {
+            let mut e = BuffEffect::new(BuffGroup::None);
+            e.faction = Faction::Alliance;
+            e.stat_multiplier = 1.1;
+            e
+        }
+        Buff::BlessingOfWisdom => {
+            let mut e = BuffEffect::new(BuffGroup::BlessingOfWisdom);
+            e.faction = Faction::Alliance;
+            e.stats.mp5 = 30.0;
+            e
+        }
+        Buff::ImpBlessingOfWisdom => {
+            let mut e = BuffEffect::new(BuffGroup::BlessingOfWisdom);
+            e.faction = Faction::Alliance;
+            e.stats.mp5 = 36.0;
+            e
+        }
+        Buff::DivineSpirit => {
+            let mut e = BuffEffect::new(BuffGroup::None);
+            e.stats.spi = 40.0;
+            e
+        }
+        Buff::ManaSpring => {
+            // 10 every 2 seconds
+            let mut e = BuffEffect::new(BuffGroup::ManaSpring);
+            e.faction = Faction::Horde;
+            e.stats.mp5 = 25.0;
+            e
+        }
+        Buff::ImpManaSpring => {
+            let mut e = BuffEffect::new(BuffGroup::ManaSpring);
+            e.faction = Faction::Horde;
+            e.stats.mp5 = 31.0;
+            e
+        }
+        Buff::MarkOfTheWild => {
+            let mut e = BuffEffect::new(BuffGroup::MarkOfTheWild);
+            e.stats.int = 12.0;
+            e.stats.spi = 12.0;
+            e
+        }
+        Buff::ImpMarkOfTheWild => {
+            let mut e = BuffEffect::new(BuffGroup::MarkOfTheWild);
+            e.stats.int = 16.0;
+            e.stats.spi = 16.0;
+            e
+        }
+        Buff::MoonkinAura => {
+            let mut e = BuffEffect::new(BuffGroup::None);
+            e.stats.crit = 3.0;
+            e
+        }
+        Buff::FlaskOfDistilledWisdom => {
+            let mut e = BuffEffect::new(BuffGroup::Flask);
+            e.stats.mana = 2000.0;
+            e
+        }
+        Buff::FlaskOfSupremePower => {
+            let mut e = BuffEffect::new(BuffGroup::Flask);
+            e.stats.sp = 150.0;
+            e
+        }
+        Buff::ArcaneElixir => {
+            let mut e = BuffEffect::new(BuffGroup::ArcaneElixir);
+            e.stats.sp = 20.0;
+            e
+        }
+        Buff::GreaterArcaneElixir => {
+            let mut e = BuffEffect::new(BuffGroup::ArcaneElixir);
+            e.stats.sp = 35.0;
+            e
+        }
+        Buff::ElixirOfFirepower => {
+            let mut e = BuffEffect::new(BuffGroup::FireElixir);
+            e.stats.sp_fire = 10.0;
+            e
+        }
+        Buff::ElixirOfGreaterFirepower => {
+            let mut e = BuffEffect::new(BuffGroup::FireElixir);
+            e.stats.sp_fire = 40.0;
+            e
+        }
+        Buff::ElixirOfFrostPower => {
+            let mut e = BuffEffect::new(BuffGroup::None);
+            e.stats.sp_frost = 15.0;
+            e
+        }
+        Buff::NightfinSoup => {
+            let mut e = BuffEffect::new(BuffGroup::Food);
+            e.stats.mp5 = 8.0;
+            e
+        }
+        Buff::RunnTumTuberSurprise => {
+            let mut e = BuffEffect::new(BuffGroup::Food);
+            e.stats.int = 10.0;
+            e
+        }
+        Buff::WellFed => {
+            let mut e = BuffEffect::new(BuffGroup::Food);
+            e.stats.spi = 12.0;
+            e
+        }
+        Buff::BlessedWizardOil => {
+            // Only against undead
+            let mut e = BuffEffect::new(BuffGroup::WeaponOil);
+            e.stats.sp = 60.0;
+            e
+        }
+        Buff::BrilliantManaOil => {
+            let mut e = BuffEffect::new(BuffGroup::WeaponOil);
+            e.stats.mp5 = 12.0;
+            e
+        }
+        Buff::BrilliantWizardOil => {
+            let mut e = BuffEffect::new(BuffGroup::WeaponOil);
+            e.stats.sp = 36.0;
+            e.stats.crit = 1.0;
+            e
+        }
+        Buff::WizardOil => {
+            let mut e = BuffEffect::new(BuffGroup::WeaponOil);
+            e.stats.sp = 24.0;
+            e
+        }
+        Buff::DarkmoonFaireDmg => {
+            let mut e = BuffEffect::new(BuffGroup::None);
+            e.dmg_multiplier = 1.1;
+            e
+        }
+        Buff::RallyingCry => {
+            let mut e = BuffEffect::new(BuffGroup::None);
+            e.stats.crit = 10.0;
+            e
+        }
+        Buff::SlipkiksSavvy => {
+            let mut e = BuffEffect::new(BuffGroup::None);
+            e.stats.crit = 3.0;
+            e
+        }
+        Buff::Songflower => {
+            let mut e = BuffEffect::new(BuffGroup::None);
+            e.stats.crit = 5.0;
+            e.stats.int = 15.0;
+            e.stats.spi = 15.0;
+            e
+        }
+        Buff::SoulRevival => {
+            let mut e = BuffEffect::new(BuffGroup::None);
+            e.dmg_multiplier = 1.1;
+            e
+        }
+        Buff::SpiritOfZandalar => {
+            let mut e = BuffEffect::new(BuffGroup::None);
+            e.stat_multiplier = 1.15;
+            e
+        }
+        Buff::TracesOfSilithyst => {
+            let mut e = BuffEffect::new(BuffGroup::None);
+            e.dmg_multiplier = 1.05;
+            e
+        }
+        Buff::WarchiefsBlessing => {
+            let mut e = BuffEffect::new(BuffGroup::None);
+            e.stats.mp5 = 10.0;
+            e
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stacking() {
+        let buffs = active_buffs(&[Buff::GreaterArcaneElixir, Buff::ArcaneElixir, Buff::ElixirOfGreaterFirepower, Buff::GreaterArcaneElixir], Race::Gnome);
+        assert_eq!(buffs, vec![Buff::GreaterArcaneElixir, Buff::ElixirOfGreaterFirepower]);
+
+        let buffs = active_buffs(&[Buff::ArcaneElixir, Buff::GreaterArcaneElixir, Buff::Songflower, Buff::RallyingCry], Race::Gnome);
+        assert_eq!(buffs, vec![Buff::ArcaneElixir, Buff::Songflower, Buff::RallyingCry]);
+    }
+
+    #[test]
+    fn factions() {
+        let buffs = [Buff::BlessingOfKings, Buff::ImpBlessingOfWisdom, Buff::ManaSpring, Buff::ArcaneIntellect];
+        assert_eq!(active_buffs(&buffs, Race::Human), vec![Buff::BlessingOfKings, Buff::ImpBlessingOfWisdom, Buff::ArcaneIntellect]);
+        assert_eq!(active_buffs(&buffs, Race::Undead), vec![Buff::ManaSpring, Buff::ArcaneIntellect]);
+    }
+}
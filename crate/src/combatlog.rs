@@ -0,0 +1,192 @@
+use crate::common::School;
+use crate::event::EventType;
+use crate::log::LogEntry;
+use crate::log::LogType;
+use crate::sim::SimulationResult;
+use crate::spell::SpellResult;
+
+// Flags for a friendly player in the raid and a hostile npc
+const PLAYER_FLAGS: &str = "0x514";
+const NPC_FLAGS: &str = "0xa48";
+const ENVIRONMENT_FLAGS: &str = "0x0";
+
+// Fights start at 20:00 on the first of january
+const T_START: f64 = 20.0 * 3600.0;
+
+// Converts the log of a single run to the advanced combat log format of the game
+// so it can be uploaded to combat log analyzers
+pub fn export(result: &SimulationResult) -> String {
+    let mut lines = vec![];
+
+    for entry in result.log.iter() {
+        if let Some(line) = event_line(entry) {
+            lines.push(format!("{}  {}", timestamp(entry.t), line));
+        }
+    }
+
+    let mut log = lines.join("\n");
+    log.push('\n');
+    log
+}
+
+// Long fights go on past midnight into the next days
+fn timestamp(t: f64) -> String {
+    let ms = ((T_START + t) * 1000.0).round() as u64;
+    let day = ms / 86_400_000 + 1;
+
+    format!("1/{} {:02}:{:02}:{:02}.{:03}", day, ms / 3_600_000 % 24, ms / 60_000 % 60, ms / 1000 % 60, ms % 1000)
+}
+
+fn school_mask(school: School) -> u32 {
+    match school {
+        School::Physical => 0x1,
+        School::Holy => 0x2,
+        School::Fire => 0x4,
+        School::Nature => 0x8,
+        School::Frost => 0x10,
+        School::Shadow => 0x20,
+        School::Arcane => 0x40,
+        School::None => 0x1,
+    }
+}
+
+fn source(entry: &LogEntry) -> String {
+    if entry.unit_id == 0 {
+        return format!("0x0000000000000000,\"{}\",{}", entry.unit_name, ENVIRONMENT_FLAGS);
+    }
+
+    format!("0x{:016X},\"{}\",{}", entry.unit_id, entry.unit_name, PLAYER_FLAGS)
+}
+
+// Targets are npcs, everything else happens to the unit itself
+fn dest(entry: &LogEntry) -> String {
+    if entry.target_id != 0 {
+        return format!("0xF130{:012X},\"{}\",{}", entry.target_id, entry.target_name, NPC_FLAGS);
+    }
+
+    source(entry)
+}
+
+fn nil_dest() -> String {
+    format!("0x0000000000000000,nil,{}", ENVIRONMENT_FLAGS)
+}
+
+// Dots and other spells triggered by a spell use its negative id in the sim
+fn spell(entry: &LogEntry) -> String {
+    format!("{},\"{}\",0x{:x}", entry.spell_id.abs(), entry.spell_name, school_mask(entry.school))
+}
+
+fn aura(entry: &LogEntry) -> String {
+    format!("{},\"{}\",0x{:x}", entry.aura_id, entry.aura_name, school_mask(entry.aura_school))
+}
+
+fn event_line(entry: &LogEntry) -> Option<String> {
+    match entry.log_type {
+        // Only spells with a cast time start casting in the game
+        LogType::CastStart if entry.value > 0.0 && entry.spell_id > 0 => {
+            Some(format!("SPELL_CAST_START,{},{},{}", source(entry), nil_dest(), spell(entry)))
+        }
+
+        LogType::CastSuccess if entry.spell_id > 0 => {
+            let dst = if entry.target_id != 0 { dest(entry) } else { nil_dest() };
+            Some(format!("SPELL_CAST_SUCCESS,{},{},{}", source(entry), dst, spell(entry)))
+        }
+
+        LogType::SpellImpact => {
+            let prefix = format!("{},{},{}", source(entry), dest(entry), spell(entry));
+
+            if entry.spell_result == SpellResult::Miss {
+                let miss = if entry.is_resisted { "RESIST" } else { "MISS" };
+                return Some(format!("SPELL_MISSED,{},{}", prefix, miss));
+            }
+
+            let event = if entry.is_periodic { "SPELL_PERIODIC_DAMAGE" } else { "SPELL_DAMAGE" };
+            let crit = if entry.spell_result == SpellResult::Crit { "1" } else { "nil" };
+
+            Some(format!(
+                "{},{},{},0x{:x},{},0,0,{},nil,nil",
+                event, prefix, entry.value.round() as i64, school_mask(entry.school), entry.resist.round() as i64, crit
+            ))
+        }
+
+        LogType::AuraGain | LogType::AuraExpire => {
+            let event = if entry.log_type == LogType::AuraGain { "SPELL_AURA_APPLIED" } else { "SPELL_AURA_REMOVED" };
+            let kind = if entry.target_id != 0 { "DEBUFF" } else { "BUFF" };
+
+            Some(format!("{},{},{},{},{}", event, source(entry), dest(entry), aura(entry), kind))
+        }
+
+        // Regen ticks are left out like in the game, other sources are named by the sim
+        LogType::Mana if entry.value > 0.0 && entry.event_type != EventType::ManaRegen => {
+            let gain = format!("0,\"{}\",0x1", entry.mana_source);
+            Some(format!("SPELL_ENERGIZE,{},{},{},{},0", source(entry), dest(entry), gain, entry.value.round() as i64))
+        }
+
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(log_type: LogType) -> LogEntry {
+        LogEntry {
+            log_type,
+            t: 65.25,
+            unit_id: 2,
+            unit_name: String::from("Mage"),
+            target_id: 1,
+            target_name: String::from("Target"),
+            spell_id: 10151,
+            spell_name: String::from("Fireball"),
+            school: School::Fire,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn lines() {
+        assert_eq!(timestamp(65.25), "1/1 20:01:05.250");
+        assert_eq!(timestamp(5.0 * 3600.0), "1/2 01:00:00.000");
+
+        let mut e = entry(LogType::SpellImpact);
+        e.value = 1234.4;
+        e.resist = 100.0;
+        e.spell_result = SpellResult::Crit;
+        assert_eq!(
+            event_line(&e).unwrap(),
+            "SPELL_DAMAGE,0x0000000000000002,\"Mage\",0x514,0xF130000000000001,\"Target\",0xa48,10151,\"Fireball\",0x4,1234,0x4,100,0,0,1,nil,nil"
+        );
+
+        e.spell_result = SpellResult::Miss;
+        assert!(event_line(&e).unwrap().starts_with("SPELL_MISSED,"));
+        assert!(event_line(&e).unwrap().ends_with(",MISS"));
+        e.is_resisted = true;
+        assert!(event_line(&e).unwrap().ends_with(",RESIST"));
+
+        let mut e = entry(LogType::AuraGain);
+        e.target_id = 0;
+        e.aura_id = 12042;
+        e.aura_name = String::from("Arcane Power");
+        e.aura_school = School::Arcane;
+        assert!(event_line(&e).unwrap().ends_with(",0x0000000000000002,\"Mage\",0x514,12042,\"Arcane Power\",0x40,BUFF"));
+
+        let mut e = entry(LogType::Mana);
+        e.value = 120.0;
+        e.event_type = EventType::ManaRegen;
+        e.mana_source = String::from("Regen");
+        assert!(event_line(&e).is_none());
+        e.event_type = EventType::ManaGain;
+        e.mana_source = String::from("Mana Ruby");
+        assert!(event_line(&e).unwrap().ends_with(",0,\"Mana Ruby\",0x1,120,0"));
+        assert!(event_line(&entry(LogType::Debug)).is_none());
+
+        let mut e = entry(LogType::CastStart);
+        assert!(event_line(&e).is_none());
+        e.value = 3.0;
+        assert!(event_line(&e).unwrap().starts_with("SPELL_CAST_START,"));
+        e.spell_id = -10151;
+        assert!(event_line(&e).is_none());
+    }
+}
@@ -7,6 +7,27 @@ pub enum Race {
     Human,
     Troll,
     Undead,
+    // Not available for mages
+    NightElf,
+    Dwarf,
+    Orc,
+    Tauren,
+}
+
+impl Race {
+    pub fn is_horde(&self) -> bool {
+        matches!(self, Race::Orc | Race::Tauren | Race::Troll | Race::Undead)
+    }
+}
+
+#[derive(Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Class {
+    #[default]
+    Mage,
+    Druid,
+    Priest,
+    Shaman,
+    Warlock,
 }
 
 #[derive(Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
@@ -31,4 +52,21 @@ pub fn set_panic_hook() {
     // https://github.com/rustwasm/console_error_panic_hook#readme
     #[cfg(feature = "console_error_panic_hook")]
     console_error_panic_hook::set_once();
-}
\ No newline at end of file
+}
+
+// Mean and the half width of its 95% confidence interval
+pub fn mean_ci(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    if n == 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let mean = values.iter().sum::<f64>() / n;
+    if n < 2.0 {
+        return (mean, 0.0);
+    }
+
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+
+    (mean, 1.96 * (variance / n).sqrt())
+}
@@ -1,22 +1,53 @@
 use crate::apl::Apl;
+use crate::apl::AplList;
+use crate::buff::Buff;
+use crate::common::Class;
 use crate::common::Race;
+use crate::debuff::DebuffConfig;
+use crate::warlock::Curse;
 use crate::stats::Stats;
 use serde::{Serialize, Deserialize};
 
 #[derive(Default, Serialize, Deserialize, Clone)]
 pub struct PlayerConfig {
     pub name: String,
+    #[serde(default)]
+    pub class: Class,
     pub race: Race,
     pub stats: Stats,
     pub level: i32,
     pub talents: Vec<u8>,
     pub items: Vec<i32>,
     pub apl: Apl,
+    // Stats only come from gear, race base stats and modifiers are added by the sim
+    #[serde(default)]
+    pub gear_stats: bool,
+    // Percent of health missing, used for troll berserking
+    #[serde(default)]
+    pub missing_health: f64,
     // Buffs
     pub mage_armor: bool,
     pub dmf_dmg: bool,
     pub soul_revival: bool,
     pub traces_of_silithyst: bool,
+    // Stat buffs are only added to gear only stats, damage buffs always apply
+    #[serde(default)]
+    pub buffs: Vec<Buff>,
+    // The curse a warlock keeps up
+    #[serde(default)]
+    pub curse: Curse,
+}
+
+// A raid member that hands out buffs to the players
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct SupportConfig {
+    pub class: Class,
+    pub name: String,
+    // Player ids that receive the buff, in order of priority
+    pub player_ids: Vec<i32>,
+    // Times to cast at, cast on cooldown when empty
+    #[serde(default)]
+    pub schedule: Vec<f64>,
 }
 
 #[derive(Default, Serialize, Deserialize, Clone)]
@@ -27,13 +58,31 @@ pub struct Config {
     pub avg_spell_dmg: bool,
     pub target_level: i32,
     pub target_resistance: i32,
+    #[serde(default)]
+    pub target_health: f64,
+    // Targets can be frozen by frost nova and frostbite, bosses can't
+    #[serde(default)]
+    pub target_freezable: bool,
     pub targets: i32,
     pub distance: i32,
     pub reaction_time: f64,
     pub player_delay: f64,
+    // Width in seconds of the timeline buckets, no timeline when 0
+    #[serde(default)]
+    pub timeline_bucket: f64,
+    // Keep the result of every iteration in multi-iteration runs
+    #[serde(default)]
+    pub keep_iterations: bool,
     pub players: Vec<PlayerConfig>,
+    // Apl lists shared by all players
+    #[serde(default)]
+    pub apl_lists: Vec<AplList>,
     // Debuffs
     pub curse_of_elements: bool,
     pub curse_of_shadows: bool,
     pub judgement_of_wisdom: bool,
+    #[serde(default)]
+    pub debuffs: Vec<DebuffConfig>,
+    #[serde(default)]
+    pub supports: Vec<SupportConfig>,
 }
\ No newline at end of file
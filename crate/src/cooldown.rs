@@ -1,3 +1,4 @@
+use crate::common::School;
 use std::collections::HashMap;
 
 pub const TRINKET_POWER: i32 = 1;
@@ -9,6 +10,7 @@ pub struct Cooldown {
     pub duration: f64,
     pub t_gained: f64,
     pub t_expires: f64,
+    pub school: School,
     pub is_hidden: bool,
 }
 
@@ -0,0 +1,85 @@
+use crate::aura;
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use serde::{Serialize, Deserialize};
+
+// Debuffs applied to the targets by the rest of the raid
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Debuff {
+    CurseOfElements,
+    CurseOfShadows,
+    ImpShadowBolt,
+    JudgementOfWisdom,
+    Nightfall,
+    Stormstrike,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct DebuffConfig {
+    pub debuff: Debuff,
+    // Percent of the time the debuff is up
+    pub uptime: f64,
+    // Time of the first application
+    pub t_apply: f64,
+    // Procs per minute, replaces uptime when set
+    pub proc_rate: f64,
+}
+
+impl Default for DebuffConfig {
+    fn default() -> Self {
+        Self {
+            debuff: Debuff::CurseOfElements,
+            uptime: 100.0,
+            t_apply: 0.0,
+            proc_rate: 0.0,
+        }
+    }
+}
+
+impl DebuffConfig {
+    pub fn new(debuff: Debuff) -> Self {
+        Self { debuff, ..Default::default() }
+    }
+}
+
+pub fn aura(debuff: Debuff) -> aura::Aura {
+    match debuff {
+        Debuff::CurseOfElements => aura::curse_of_elements(),
+        Debuff::CurseOfShadows => aura::curse_of_shadows(),
+        // Applied by a warlock with all 5 points
+        Debuff::ImpShadowBolt => aura::shadow_vulnerability(5),
+        Debuff::JudgementOfWisdom => aura::judgement_of_wisdom(),
+        Debuff::Nightfall => aura::spell_vulnerability(),
+        Debuff::Stormstrike => aura::stormstrike(),
+    }
+}
+
+// When the debuff is applied during a fight and for how long
+// Uptime is spread evenly over windows of the aura duration, procs happen at random
+pub fn applications(config: &DebuffConfig, duration: f64, rng: &mut ChaCha8Rng) -> Vec<(f64, f64)> {
+    let mut result = vec![];
+    let aura_duration = aura(config.debuff).duration;
+    let mut t = config.t_apply.max(0.0);
+
+    if config.proc_rate > 0.0 {
+        loop {
+            t+= -(1.0 - rng.gen::<f64>()).ln() * 60.0 / config.proc_rate;
+            if t > duration {
+                break;
+            }
+            result.push((t, aura_duration));
+        }
+    } else if config.uptime >= 100.0 {
+        if t <= duration {
+            result.push((t, duration - t + 1.0));
+        }
+    } else if config.uptime > 0.0 {
+        while t <= duration {
+            result.push((t, aura_duration * config.uptime / 100.0));
+            t+= aura_duration;
+        }
+    }
+
+    result
+}
@@ -3,9 +3,10 @@ use crate::aura;
 use crate::cooldown;
 use crate::spell;
 use crate::unit::Unit;
+use serde::{Serialize, Deserialize};
 use std::collections::VecDeque;
 
-#[derive(Default, PartialEq, Eq, Copy, Clone)]
+#[derive(Default, Serialize, Deserialize, PartialEq, Eq, Copy, Clone, Debug)]
 pub enum EventType {
     #[default]
     None,
@@ -0,0 +1,136 @@
+use crate::sim::SimulationsResult;
+
+// Output formats for batch results, see export()
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    // One row per iteration
+    Csv,
+    // One json object per iteration
+    Jsonl,
+    // One row per player with the means of all iterations
+    PlayersCsv,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "csv" => Some(Self::Csv),
+            "jsonl" => Some(Self::Jsonl),
+            "players_csv" => Some(Self::PlayersCsv),
+            _ => None,
+        }
+    }
+}
+
+pub fn export(result: &SimulationsResult, format: Format) -> String {
+    match format {
+        Format::Csv => iterations_csv(result),
+        Format::Jsonl => iterations_jsonl(result),
+        Format::PlayersCsv => players_csv(result),
+    }
+}
+
+// Iterations are only kept when keep_iterations is set in the config
+pub fn iterations_csv(result: &SimulationsResult) -> String {
+    let mut header = vec![
+        String::from("iteration"),
+        String::from("seed"),
+        String::from("duration"),
+        String::from("dps"),
+        String::from("ignite_dps"),
+    ];
+    for player in result.players.iter() {
+        header.push(csv_field(&format!("{} dps", player.name)));
+    }
+
+    let mut lines = vec![header.join(",")];
+    for r in result.iteration_results.iter() {
+        let mut row = vec![
+            r.iteration.to_string(),
+            r.seed.to_string(),
+            r.duration.to_string(),
+            r.dps.to_string(),
+            r.ignite_dps.to_string(),
+        ];
+        for dps in r.player_dps.iter() {
+            row.push(dps.to_string());
+        }
+        lines.push(row.join(","));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+pub fn iterations_jsonl(result: &SimulationsResult) -> String {
+    let mut out = String::new();
+
+    for r in result.iteration_results.iter() {
+        out.push_str(&serde_json::to_string(r).unwrap());
+        out.push('\n');
+    }
+
+    out
+}
+
+pub fn players_csv(result: &SimulationsResult) -> String {
+    let mut lines = vec![String::from("name,class,dps,ignite_dps,tps,oom_rate,dmg_per_mana")];
+
+    for p in result.players.iter() {
+        lines.push(format!(
+            "{},{:?},{},{},{},{},{}",
+            csv_field(&p.name), p.class, p.dps, p.ignite_dps, p.tps, p.mana.oom_rate, p.mana.dmg_per_mana
+        ));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        return format!("\"{}\"", value.replace('"', "\"\""));
+    }
+
+    value.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::IterationResult;
+    use crate::sim::PlayerResult;
+
+    fn result() -> SimulationsResult {
+        SimulationsResult {
+            iterations: 2,
+            players: vec![
+                PlayerResult { name: String::from("Mage"), dps: 1000.0, ..Default::default() },
+                PlayerResult { name: String::from("Frost, \"Bolt\""), dps: 900.0, ..Default::default() },
+            ],
+            iteration_results: vec![
+                IterationResult { iteration: 1, seed: 10, duration: 180.0, dps: 1900.5, ignite_dps: 100.0, player_dps: vec![1000.5, 900.0] },
+                IterationResult { iteration: 2, seed: 11, duration: 180.0, dps: 1800.0, ignite_dps: 50.0, player_dps: vec![950.0, 850.0] },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn csv() {
+        assert_eq!(
+            iterations_csv(&result()),
+            "iteration,seed,duration,dps,ignite_dps,Mage dps,\"Frost, \"\"Bolt\"\" dps\"\n1,10,180,1900.5,100,1000.5,900\n2,11,180,1800,50,950,850\n"
+        );
+        assert_eq!(players_csv(&result()).lines().count(), 3);
+    }
+
+    #[test]
+    fn jsonl() {
+        let out = iterations_jsonl(&result());
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2);
+
+        let r: IterationResult = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(r.seed, 11);
+        assert_eq!(r.player_dps, vec![950.0, 850.0]);
+    }
+}
@@ -0,0 +1,247 @@
+use crate::apl;
+use crate::common::Class;
+use crate::config::Config;
+use crate::mage::Mage;
+use crate::support::Support;
+use crate::unit::Unit;
+use crate::warlock::Warlock;
+use serde::{Serialize, Deserialize};
+use std::fmt;
+
+type SpawnPlayer = fn(Config, i32) -> Box<dyn Unit>;
+type SpawnSupport = fn(Config, usize) -> Box<dyn Unit>;
+
+// What the sim knows about a class
+pub struct ClassEntry {
+    pub class: Class,
+    // Spawns the class as a player, None for classes that only support the raid
+    pub spawn_player: Option<SpawnPlayer>,
+    // Spawns the class as a support unit
+    pub spawn_support: Option<SpawnSupport>,
+    // Classes without an apl run a fixed rotation
+    pub has_apl: bool,
+}
+
+pub fn registry() -> Vec<ClassEntry> {
+    vec![
+        ClassEntry {
+            class: Class::Mage,
+            spawn_player: Some(spawn_mage),
+            spawn_support: None,
+            has_apl: true,
+        },
+        ClassEntry {
+            class: Class::Warlock,
+            spawn_player: Some(spawn_warlock),
+            spawn_support: None,
+            has_apl: false,
+        },
+        ClassEntry {
+            class: Class::Druid,
+            spawn_player: None,
+            spawn_support: Some(spawn_support),
+            has_apl: false,
+        },
+        ClassEntry {
+            class: Class::Priest,
+            spawn_player: None,
+            spawn_support: Some(spawn_support),
+            has_apl: false,
+        },
+        ClassEntry {
+            class: Class::Shaman,
+            spawn_player: None,
+            spawn_support: Some(spawn_support),
+            has_apl: false,
+        },
+    ]
+}
+
+pub fn class_entry(class: Class) -> ClassEntry {
+    registry().into_iter().find(|entry| entry.class == class).expect("CLASS_NOT_REGISTERED")
+}
+
+pub fn spawn_player(config: Config, id: i32) -> Box<dyn Unit> {
+    let class = config.players[(id as usize) - 1].class;
+    let spawn = class_entry(class).spawn_player.expect("CLASS_NOT_PLAYABLE");
+
+    spawn(config, id)
+}
+
+pub fn spawn_support_unit(config: Config, index: usize) -> Box<dyn Unit> {
+    let class = config.supports[index].class;
+    let spawn = class_entry(class).spawn_support.expect("CLASS_NOT_SUPPORT");
+
+    spawn(config, index)
+}
+
+// A problem that prevents the config from being simulated
+// It is about either a player or a support, ids start at 1 and the other one is 0
+#[derive(Default, Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct ConfigError {
+    pub player_id: i32,
+    pub support_id: i32,
+    pub text: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.player_id != 0 {
+            write!(f, "Player {}: {}", self.player_id, self.text)
+        } else {
+            write!(f, "Support {}: {}", self.support_id, self.text)
+        }
+    }
+}
+
+pub fn validate_config(config: &Config) -> Vec<ConfigError> {
+    let mut errors = vec![];
+
+    for (i, player) in config.players.iter().enumerate() {
+        let entry = class_entry(player.class);
+        let mut error = |text: String| errors.push(ConfigError { player_id: i as i32 + 1, support_id: 0, text });
+
+        if entry.spawn_player.is_none() {
+            error(format!("{:?} can only be used as a support", player.class));
+            continue;
+        }
+
+        if !entry.has_apl && !apl_action_keys(&player.apl).is_empty() {
+            error(format!("{:?} uses a fixed rotation and can't have an apl", player.class));
+        }
+    }
+
+    for (i, support) in config.supports.iter().enumerate() {
+        if class_entry(support.class).spawn_support.is_none() {
+            errors.push(ConfigError { player_id: 0, support_id: i as i32 + 1, text: format!("{:?} can't be used as a support", support.class) });
+        }
+    }
+
+    errors
+}
+
+// All problems in one message, for callers that can only report a string
+pub fn check_config(config: &Config) -> Result<(), String> {
+    let errors = validate_config(config);
+    if errors.is_empty() {
+        return Ok(());
+    }
+
+    Err(errors.iter().map(|error| error.to_string()).collect::<Vec<String>>().join("\n"))
+}
+
+// Every action used in the apl, including sequences and lists
+fn apl_action_keys(apl: &apl::Apl) -> Vec<apl::AplActionKey> {
+    fn add(action: &apl::AplAction, keys: &mut Vec<apl::AplActionKey>) {
+        if action.key != apl::AplActionKey::None && !keys.contains(&action.key) {
+            keys.push(action.key.clone());
+        }
+        for a in action.sequence.iter() {
+            add(a, keys);
+        }
+    }
+
+    let mut keys = vec![];
+    let lists = apl.lists.iter().flat_map(|list| list.items.iter());
+    for item in apl.items.iter().chain(lists) {
+        add(&item.action, &mut keys);
+    }
+
+    keys
+}
+
+fn spawn_mage(config: Config, id: i32) -> Box<dyn Unit> {
+    let mut player = Box::new(Mage::new());
+    let index = (id as usize) - 1;
+
+    player.id = id;
+    player.name.clone_from(&config.players[index].name);
+    player.level = config.players[index].level;
+
+    player.set_config(config);
+    player.stats = player.player_stats();
+    player.reset();
+
+    player
+}
+
+fn spawn_warlock(config: Config, id: i32) -> Box<dyn Unit> {
+    let mut player = Box::new(Warlock::new());
+    let index = (id as usize) - 1;
+
+    player.id = id;
+    player.name.clone_from(&config.players[index].name);
+    player.level = config.players[index].level;
+
+    player.set_config(config);
+    player.reset();
+
+    player
+}
+
+fn spawn_support(config: Config, index: usize) -> Box<dyn Unit> {
+    let mut support = Box::new(Support::new());
+
+    support.id = (config.players.len() + index) as i32 + 1;
+    support.name.clone_from(&config.supports[index].name);
+    if support.name.is_empty() {
+        support.name = format!("{:?}", config.supports[index].class);
+    }
+
+    support.set_index(index);
+    support.set_config(config);
+    support.reset();
+
+    support
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PlayerConfig;
+    use crate::config::SupportConfig;
+
+    fn player(class: Class, keys: Vec<apl::AplActionKey>) -> PlayerConfig {
+        let mut player = PlayerConfig { class, ..Default::default() };
+        for key in keys {
+            let mut item = apl::AplItem::default();
+            item.action.key = key;
+            player.apl.items.push(item);
+        }
+        player
+    }
+
+    #[test]
+    fn validate() {
+        let mut config = Config {
+            players: vec![player(Class::Mage, vec![apl::AplActionKey::Fireball]), player(Class::Warlock, vec![])],
+            supports: vec![SupportConfig { class: Class::Priest, player_ids: vec![1, 2], ..Default::default() }],
+            ..Default::default()
+        };
+        assert!(validate_config(&config).is_empty());
+
+        assert!(check_config(&config).is_ok());
+
+        config.players.push(player(Class::Warlock, vec![apl::AplActionKey::Fireball, apl::AplActionKey::Frostbolt]));
+        config.players.push(player(Class::Shaman, vec![]));
+        // Old apls with actions of support units still run, the mage skips them
+        config.players.push(player(Class::Mage, vec![apl::AplActionKey::Fireball, apl::AplActionKey::ManaTide]));
+        config.supports.push(SupportConfig { class: Class::Mage, ..Default::default() });
+        let errors: Vec<String> = validate_config(&config).iter().map(|error| error.to_string()).collect();
+        assert_eq!(errors, vec![
+            String::from("Player 3: Warlock uses a fixed rotation and can't have an apl"),
+            String::from("Player 4: Shaman can only be used as a support"),
+            String::from("Support 2: Mage can't be used as a support"),
+        ]);
+        assert_eq!(validate_config(&config)[1], ConfigError { player_id: 4, support_id: 0, text: String::from("Shaman can only be used as a support") });
+        assert_eq!(check_config(&config).unwrap_err(), errors.join("\n"));
+    }
+
+    #[test]
+    fn every_class_is_registered() {
+        for class in [Class::Mage, Class::Druid, Class::Priest, Class::Shaman, Class::Warlock] {
+            let entry = class_entry(class);
+            assert!(entry.spawn_player.is_some() || entry.spawn_support.is_some());
+        }
+    }
+}
@@ -9,13 +9,25 @@ mod config;
 mod item;
 mod spell;
 mod aura;
+mod buff;
+mod combatlog;
 mod cooldown;
+mod debuff;
 mod log;
+mod report;
+mod rng;
 mod target;
 mod unit;
 mod apl;
+mod lint;
 mod mage;
+mod profileset;
+mod scaling;
+mod support;
+mod warlock;
 mod event;
+mod export;
+mod factory;
 mod sim;
 
 use wasm_bindgen::prelude::*;
@@ -35,14 +47,118 @@ const MANA_TICK_T: f64 = 2.0;
  * - Convert result to json
  */
 
+#[wasm_bindgen]
+/// # Panics
+/// # Errors
+pub fn run_simulation(cfg: &JsValue) -> Result<JsValue, JsValue> {
+    common::set_panic_hook();
+
+    let config = cfg.into_serde().unwrap();
+    let result = sim::run_single(config).map_err(|error| JsValue::from_str(&error))?;
+
+    Ok(serde_wasm_bindgen::to_value(&result).unwrap())
+}
+
+#[wasm_bindgen]
+/// # Panics
+/// # Errors
+pub fn replay_simulation(cfg: &JsValue, iteration: i32) -> Result<JsValue, JsValue> {
+    common::set_panic_hook();
+
+    let config = cfg.into_serde().unwrap();
+    let result = sim::replay(config, iteration).map_err(|error| JsValue::from_str(&error))?;
+
+    Ok(serde_wasm_bindgen::to_value(&result).unwrap())
+}
+
+#[wasm_bindgen]
+/// # Panics
+/// # Errors
+pub fn run_simulations(cfg: &JsValue, iterations: i32) -> Result<JsValue, JsValue> {
+    common::set_panic_hook();
+
+    let config = cfg.into_serde().unwrap();
+    let result = sim::run_multiple(config, iterations).map_err(|error| JsValue::from_str(&error))?;
+
+    Ok(serde_wasm_bindgen::to_value(&result).unwrap())
+}
+
+#[wasm_bindgen]
+/// # Panics
+/// # Errors
+pub fn run_profile_set(cfg: &JsValue, profiles: &JsValue, iterations: i32) -> Result<JsValue, JsValue> {
+    common::set_panic_hook();
+
+    let config = cfg.into_serde().unwrap();
+    let profiles: Vec<profileset::Profile> = profiles.into_serde().unwrap();
+    let result = profileset::run(config, &profiles, iterations).map_err(|error| JsValue::from_str(&error))?;
+
+    Ok(serde_wasm_bindgen::to_value(&result).unwrap())
+}
+
+#[wasm_bindgen]
+/// # Panics
+/// # Errors
+pub fn run_stat_scaling(cfg: &JsValue, scaling: &JsValue, iterations: i32) -> Result<JsValue, JsValue> {
+    common::set_panic_hook();
+
+    let config = cfg.into_serde().unwrap();
+    let scaling = scaling.into_serde().unwrap();
+    let result = scaling::run(config, &scaling, iterations).map_err(|error| JsValue::from_str(&error))?;
+
+    Ok(serde_wasm_bindgen::to_value(&result).unwrap())
+}
+
+#[wasm_bindgen]
+/// # Panics
+/// # Errors
+pub fn export_simulations(cfg: &JsValue, iterations: i32, format: &str) -> Result<String, JsValue> {
+    common::set_panic_hook();
+
+    let mut config: config::Config = cfg.into_serde().unwrap();
+    config.keep_iterations = true;
+    let format = export::Format::from_name(format).ok_or_else(|| JsValue::from_str(&format!("Unknown export format {}", format)))?;
+    let result = sim::run_multiple(config, iterations).map_err(|error| JsValue::from_str(&error))?;
+
+    Ok(export::export(&result, format))
+}
+
+#[wasm_bindgen]
+/// # Panics
+/// # Errors
+pub fn report_simulations(cfg: &JsValue, iterations: i32) -> Result<String, JsValue> {
+    common::set_panic_hook();
+
+    let mut config: config::Config = cfg.into_serde().unwrap();
+    let result = sim::run_multiple(config.clone(), iterations).map_err(|error| JsValue::from_str(&error))?;
+
+    // The log of the median iteration stands for the whole run
+    config.rng_seed = result.rng_seed;
+    let run = sim::replay(config, result.median_iteration.iteration).map_err(|error| JsValue::from_str(&error))?;
+
+    Ok(report::html(&result, Some(&run)))
+}
+
+#[wasm_bindgen]
+/// # Panics
+/// # Errors
+pub fn export_combat_log(cfg: &JsValue) -> Result<String, JsValue> {
+    common::set_panic_hook();
+
+    let config = cfg.into_serde().unwrap();
+    let result = sim::run_single(config).map_err(|error| JsValue::from_str(&error))?;
+
+    Ok(combatlog::export(&result))
+}
+
 #[wasm_bindgen]
 #[must_use]
 /// # Panics
-pub fn run_simulation(cfg: &JsValue) -> JsValue {
+pub fn lint_apl(cfg: &JsValue, player_id: i32) -> JsValue {
     common::set_panic_hook();
 
     let config = cfg.into_serde().unwrap();
-    let result = sim::run_single(config);
+    let result = lint::lint_apl(config, player_id);
 
     serde_wasm_bindgen::to_value(&result).unwrap()
 }
@@ -50,11 +166,11 @@ pub fn run_simulation(cfg: &JsValue) -> JsValue {
 #[wasm_bindgen]
 #[must_use]
 /// # Panics
-pub fn run_simulations(cfg: &JsValue, iterations: i32) -> JsValue {
+pub fn validate_config(cfg: &JsValue) -> JsValue {
     common::set_panic_hook();
 
     let config = cfg.into_serde().unwrap();
-    let result = sim::run_multiple(config, iterations);
+    let result = factory::validate_config(&config);
 
     serde_wasm_bindgen::to_value(&result).unwrap()
-}
\ No newline at end of file
+}
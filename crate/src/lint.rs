@@ -0,0 +1,281 @@
+use crate::apl;
+use crate::common::Class;
+use crate::config::Config;
+use crate::event::EventType;
+use crate::factory;
+use crate::mage::Mage;
+use crate::target::Target;
+use crate::unit::Unit;
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+
+#[derive(Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AplLintLevel {
+    #[default]
+    Warning,
+    Error,
+}
+
+// A problem found in an apl
+// The path points into the apl tree, ie. "items.2.condition.conditions.0"
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct AplLint {
+    pub level: AplLintLevel,
+    pub path: String,
+    pub text: String,
+}
+
+// Public function to lint the apl of one player
+pub fn lint_apl(config: Config, player_id: i32) -> Vec<AplLint> {
+    if player_id < 1 || player_id as usize > config.players.len() {
+        return vec![];
+    }
+
+    // Other classes are only checked against their registered apl actions
+    if config.players[(player_id as usize) - 1].class != Class::Mage {
+        return factory::validate_config(&config).into_iter()
+            .filter(|error| error.player_id == player_id)
+            .map(|error| AplLint { level: AplLintLevel::Error, path: String::new(), text: error.text })
+            .collect();
+    }
+
+    // A fresh mage tells us what the player can do with their talents, items and race
+    let mut mage = Mage::new();
+    mage.id = player_id;
+    mage.set_config(config);
+
+    let mut linter = AplLinter::new(&mage);
+    linter.lint();
+    linter.lints
+}
+
+struct AplLinter<'a> {
+    mage: &'a Mage,
+    targets: HashMap<i32, Target>,
+    lints: Vec<AplLint>,
+    shared_lists: Vec<usize>,
+}
+
+impl<'a> AplLinter<'a> {
+    fn new(mage: &'a Mage) -> Self {
+        Self {
+            mage,
+            targets: HashMap::new(),
+            lints: vec![],
+            shared_lists: vec![],
+        }
+    }
+
+    fn config(&self) -> &'a Config {
+        self.mage.config.as_ref().unwrap()
+    }
+
+    fn apl(&self) -> &'a apl::Apl {
+        &self.config().players[(self.mage.id as usize) - 1].apl
+    }
+
+    fn warning(&mut self, path: String, text: String) {
+        self.lints.push(AplLint { level: AplLintLevel::Warning, path, text });
+    }
+
+    fn error(&mut self, path: String, text: String) {
+        self.lints.push(AplLint { level: AplLintLevel::Error, path, text });
+    }
+
+    fn lint(&mut self) {
+        self.lint_items(&self.apl().items, String::from("items"));
+
+        for (i, list) in self.apl().lists.iter().enumerate() {
+            self.lint_items(&list.items, format!("lists.{}.items", i));
+        }
+
+        // Shared lists are only linted when this player uses them
+        let mut n = 0;
+        while n < self.shared_lists.len() {
+            let i = self.shared_lists[n];
+            self.lint_items(&self.config().apl_lists[i].items, format!("apl_lists.{}.items", i));
+            n+= 1;
+        }
+    }
+
+    fn lint_items(&mut self, items: &[apl::AplItem], path: String) {
+        let mut blocked_by: Option<usize> = None;
+
+        for (i, item) in items.iter().enumerate() {
+            let path = format!("{}.{}", path, i);
+
+            if let Some(j) = blocked_by {
+                self.warning(path.clone(), format!("Unreachable, item {} above is always used", j + 1));
+            }
+
+            self.lint_condition(&item.condition, format!("{}.condition", path));
+            self.lint_action(&item.action, format!("{}.action", path));
+
+            if blocked_by.is_none() && self.is_always_true(&item.condition) && self.is_always_available(&item.action) {
+                blocked_by = Some(i);
+            }
+        }
+    }
+
+    fn lint_condition(&mut self, condition: &apl::AplCondition, path: String) {
+        match condition.condition_type {
+            apl::AplConditionType::And | apl::AplConditionType::Or => {
+                for (i, cond) in condition.conditions.iter().enumerate() {
+                    self.lint_condition(cond, format!("{}.conditions.{}", path, i));
+                }
+            }
+            apl::AplConditionType::Cmp => {
+                if condition.values.len() != 2 {
+                    self.error(path.clone(), format!("Comparison needs 2 values, found {}", condition.values.len()));
+                }
+                if condition.op == apl::AplConditionOp::None {
+                    self.error(path, String::from("Comparison has no operator"));
+                }
+            }
+            apl::AplConditionType::Not => {
+                if condition.conditions.len() != 1 {
+                    self.error(path.clone(), format!("Not needs 1 condition, found {}", condition.conditions.len()));
+                }
+                for (i, cond) in condition.conditions.iter().enumerate() {
+                    self.lint_condition(cond, format!("{}.conditions.{}", path, i));
+                }
+            }
+            apl::AplConditionType::False | apl::AplConditionType::True => {
+                if condition.values.len() != 1 {
+                    self.error(path, format!("{:?} needs 1 value, found {}", condition.condition_type, condition.values.len()));
+                }
+            }
+            apl::AplConditionType::None => {}
+        }
+    }
+
+    fn lint_action(&mut self, action: &apl::AplAction, path: String) {
+        match action.key {
+            apl::AplActionKey::None | apl::AplActionKey::Wait => {}
+            apl::AplActionKey::CallList | apl::AplActionKey::RunList => {
+                if self.apl().lists.iter().any(|list| list.name == action.list) {
+                    // Linted with the player lists
+                } else if let Some(i) = self.config().apl_lists.iter().position(|list| list.name == action.list) {
+                    if !self.shared_lists.contains(&i) {
+                        self.shared_lists.push(i);
+                    }
+                } else {
+                    self.error(path, format!("Unknown list '{}'", action.list));
+                }
+            }
+            apl::AplActionKey::Innervate | apl::AplActionKey::ManaTide | apl::AplActionKey::PowerInfusion => {
+                self.warning(path, format!("{:?} is cast by support units and is skipped, add one to the raid instead", action.key));
+            }
+            apl::AplActionKey::Sequence => {
+                if action.sequence.is_empty() {
+                    self.warning(path.clone(), String::from("Sequence is empty"));
+                } else if action.sequence.iter().all(|a| self.is_trigger(a)) {
+                    self.warning(path.clone(), String::from("Sequence only contains spells that are not on the global cooldown"));
+                }
+                for (i, a) in action.sequence.iter().enumerate() {
+                    if a.key == apl::AplActionKey::CallList || a.key == apl::AplActionKey::RunList {
+                        self.error(format!("{}.sequence.{}", path, i), String::from("Lists can not be used in a sequence"));
+                    } else {
+                        self.lint_action(a, format!("{}.sequence.{}", path, i));
+                    }
+                }
+            }
+            _ => {
+                if self.mage.apl_action(action, 0.0, &self.targets).event_type == EventType::None {
+                    self.error(path, format!("{:?} can never be used with these talents, items and race", action.key));
+                }
+            }
+        }
+    }
+
+    fn is_always_true(&self, condition: &apl::AplCondition) -> bool {
+        match condition.condition_type {
+            apl::AplConditionType::None => true,
+            apl::AplConditionType::And => condition.conditions.iter().all(|c| self.is_always_true(c)),
+            _ => false,
+        }
+    }
+
+    // Actions that never fail, which means nothing below them is ever reached
+    fn is_always_available(&self, action: &apl::AplAction) -> bool {
+        match action.key {
+            apl::AplActionKey::None | apl::AplActionKey::CallList => false,
+            // Running a list always ends the evaluation
+            apl::AplActionKey::Wait | apl::AplActionKey::RunList => true,
+            apl::AplActionKey::Sequence => !action.sequence.is_empty(),
+            _ => {
+                let event = self.mage.apl_action(action, 0.0, &self.targets);
+                event.spell.is_some_and(|spell| spell.cooldown == 0.0)
+            }
+        }
+    }
+
+    fn is_trigger(&self, action: &apl::AplAction) -> bool {
+        let event = self.mage.apl_action(action, 0.0, &self.targets);
+        event.spell.is_some_and(|spell| spell.is_trigger)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::tests::fireball_config;
+
+    fn item(key: apl::AplActionKey) -> apl::AplItem {
+        let mut item = apl::AplItem::default();
+        item.action.key = key;
+        item.action.target_id = 1;
+        item
+    }
+
+    fn lint(items: Vec<apl::AplItem>) -> Vec<(String, String)> {
+        let mut config = fireball_config(60.0);
+        config.players[0].apl.items = items;
+
+        lint_apl(config, 1).into_iter().map(|lint| (lint.path, lint.text)).collect()
+    }
+
+    #[test]
+    fn unreachable() {
+        let lints = lint(vec![item(apl::AplActionKey::Fireball), item(apl::AplActionKey::Frostbolt)]);
+        assert_eq!(lints, vec![(String::from("items.1"), String::from("Unreachable, item 1 above is always used"))]);
+
+        // A condition or a cooldown lets the items below run
+        let mut fireball = item(apl::AplActionKey::Fireball);
+        fireball.condition.condition_type = apl::AplConditionType::False;
+        fireball.condition.values.push(apl::AplValue::default());
+        assert!(lint(vec![fireball, item(apl::AplActionKey::Frostbolt)]).is_empty());
+        assert!(lint(vec![item(apl::AplActionKey::FireBlast), item(apl::AplActionKey::Frostbolt)]).is_empty());
+    }
+
+    #[test]
+    fn actions() {
+        // Pyroblast needs the talent
+        let lints = lint(vec![item(apl::AplActionKey::Pyroblast)]);
+        assert_eq!(lints, vec![(String::from("items.0.action"), String::from("Pyroblast can never be used with these talents, items and race"))]);
+
+        let mut call = item(apl::AplActionKey::CallList);
+        call.action.list = String::from("filler");
+        assert_eq!(lint(vec![call]), vec![(String::from("items.0.action"), String::from("Unknown list 'filler'"))]);
+
+        let lints = lint(vec![item(apl::AplActionKey::PowerInfusion)]);
+        assert_eq!(lints, vec![(String::from("items.0.action"), String::from("PowerInfusion is cast by support units and is skipped, add one to the raid instead"))]);
+    }
+
+    #[test]
+    fn conditions() {
+        let mut cmp = item(apl::AplActionKey::Frostbolt);
+        cmp.condition.condition_type = apl::AplConditionType::Cmp;
+        cmp.condition.op = apl::AplConditionOp::Gt;
+        cmp.condition.values.push(apl::AplValue::default());
+
+        let mut not = item(apl::AplActionKey::Fireball);
+        not.condition.condition_type = apl::AplConditionType::Not;
+        not.condition.conditions = vec![apl::AplCondition::default(), apl::AplCondition::default()];
+
+        assert_eq!(lint(vec![cmp, not]), vec![
+            (String::from("items.0.condition"), String::from("Comparison needs 2 values, found 1")),
+            (String::from("items.1.condition"), String::from("Not needs 1 condition, found 2")),
+        ]);
+    }
+}
@@ -1,7 +1,9 @@
+use crate::common::School;
+use crate::event::EventType;
 use crate::spell::SpellResult;
 use serde::{Serialize, Deserialize};
 
-#[derive(Default, Serialize, Deserialize, Clone)]
+#[derive(Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
 pub enum LogType {
     #[default]
     None,
@@ -29,6 +31,77 @@ pub struct LogEntry {
     pub total_dps: f64,
     pub ignite_dps: f64,
     pub value: f64,
-    pub value2: f64,
     pub spell_result: SpellResult,
-}
\ No newline at end of file
+    // What the entry is about, target 0 is the unit itself
+    #[serde(default)]
+    pub event_type: EventType,
+    #[serde(default)]
+    pub unit_id: i32,
+    #[serde(default)]
+    pub target_id: i32,
+    #[serde(default)]
+    pub target_name: String,
+    #[serde(default)]
+    pub spell_id: i32,
+    #[serde(default)]
+    pub spell_name: String,
+    #[serde(default)]
+    pub aura_id: i32,
+    #[serde(default)]
+    pub aura_name: String,
+    // Only set for auras that stack
+    #[serde(default)]
+    pub stacks: i32,
+    #[serde(default)]
+    pub cooldown_id: i32,
+    #[serde(default)]
+    pub cooldown_name: String,
+    #[serde(default)]
+    pub school: School,
+    #[serde(default)]
+    pub is_periodic: bool,
+    #[serde(default)]
+    pub resist: f64,
+    #[serde(default)]
+    pub is_resisted: bool,
+    // Where the mana of Mana entries came from, ie. "Regen" or "Mana Ruby"
+    #[serde(default)]
+    pub mana_source: String,
+    #[serde(default)]
+    pub aura_school: School,
+    #[serde(default)]
+    pub mana_before: f64,
+    #[serde(default)]
+    pub mana_after: f64,
+}
+
+impl LogEntry {
+    // Text shown in the log, with s[], a[], c[] and t[] marking spells, auras, cooldowns and targets
+    // Entries without a structured type keep the text they were given
+    pub fn describe(&self) -> String {
+        let target = if self.target_id != 0 { format!("t[{}]", self.target_name) } else { String::new() };
+
+        match self.log_type {
+            LogType::CastStart | LogType::SpellImpact if !target.is_empty() => format!("s[{}] -> {}", self.spell_name, target),
+            LogType::CastStart | LogType::CastSuccess | LogType::SpellImpact => format!("s[{}]", self.spell_name),
+            LogType::AuraGain => {
+                let mut text = format!("a[{}]", self.aura_name);
+                if self.stacks > 0 {
+                    text.push_str(&format!(" ({})", self.stacks));
+                }
+                if !target.is_empty() {
+                    text.push_str(&format!(" -> {}", target));
+                }
+                text
+            }
+            LogType::AuraExpire if !target.is_empty() => format!("a[{}] != {}", self.aura_name, target),
+            LogType::AuraExpire => format!("a[{}]", self.aura_name),
+            LogType::CooldownGain | LogType::CooldownExpire => format!("c[{}]", self.cooldown_name),
+            LogType::Mana if !self.spell_name.is_empty() => self.spell_name.clone(),
+            LogType::Mana if self.event_type == EventType::ManaRegen => String::from("Mana regen"),
+            LogType::Mana if !self.mana_source.is_empty() => self.mana_source.clone(),
+            LogType::Mana => String::from("Mana"),
+            _ => self.text.clone(),
+        }
+    }
+}
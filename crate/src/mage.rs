@@ -1,5 +1,6 @@
 use crate::apl;
 use crate::aura;
+use crate::buff;
 use crate::common;
 use crate::common::School;
 use crate::config::Config;
@@ -9,6 +10,9 @@ use crate::event::Event;
 use crate::event::EventType;
 use crate::item;
 use crate::macros::console_log;
+use crate::rng::Owner;
+use crate::rng::Rngs;
+use crate::rng::Stream;
 use crate::sim::Sim;
 use crate::spell;
 use crate::stats::Stats;
@@ -20,57 +24,60 @@ use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
 use std::collections::VecDeque;
 
-const TALENT_ARCANE_SUBLETY: usize = 0;
-const TALENT_ARCANE_FOCUS: usize = 1;
-const TALENT_IMP_ARCANE_MISSILES: usize = 2;
-const TALENT_WAND_SPEC: usize = 3;
-const TALENT_MAGIC_ABSORPTION: usize = 4;
-const TALENT_ARCANE_CONCENTRATION: usize = 5;
-const TALENT_MAGIC_ATTUNEMENT: usize = 6;
-const TALENT_IMP_ARCANE_EXPLOSION: usize = 7;
-const TALENT_ARCANE_RESILIENCE: usize = 8;
-const TALENT_IMP_MANA_SHIELD: usize = 9;
-const TALENT_IMP_COUNTERSPELL: usize = 10;
-const TALENT_ARCANE_MEDITATION: usize = 11;
-const TALENT_PRESENCE_OF_MIND: usize = 12;
-const TALENT_ARCANE_MIND: usize = 13;
-const TALENT_ARCANE_INSTABILITY: usize = 14;
-const TALENT_ARCANE_POWER: usize = 15;
-
-const TALENT_IMP_FIREBALL: usize = 16;
-const TALENT_IMPACT: usize = 17;
-const TALENT_IGNITE: usize = 18;
-const TALENT_FLAME_THROWING: usize = 19;
-const TALENT_IMP_FIRE_BLAST: usize = 20;
-const TALENT_INCINERATE: usize = 21;
-const TALENT_IMP_FLAMESTRIKE: usize = 22;
-const TALENT_PYROBLAST: usize = 23;
-const TALENT_BURNING_SOUL: usize = 24;
-const TALENT_IMP_SCORCH: usize = 25;
-const TALENT_IMP_FIRE_WARD: usize = 26;
-const TALENT_MASTER_OF_ELEMENTS: usize = 27;
-const TALENT_CRITICAL_MASS: usize = 28;
-const TALENT_BLAST_WAVE: usize = 29;
-const TALENT_FIRE_POWER: usize = 30;
-const TALENT_COMBUSTION: usize = 31;
-
-const TALENT_FROST_WARDING: usize = 32;
-const TALENT_IMP_FROSTBOLT: usize = 33;
-const TALENT_ELEMENTAL_PRECISION: usize = 34;
-const TALENT_ICE_SHARDS: usize = 35;
-const TALENT_FROSTBITE: usize = 36;
-const TALENT_IMP_FROST_NOVA: usize = 37;
-const TALENT_PERMAFROST: usize = 38;
-const TALENT_PIERCING_ICE: usize = 39;
-const TALENT_COLD_SNAP: usize = 40;
-const TALENT_IMP_BLIZZARD: usize = 41;
-const TALENT_ARCTIC_REACH: usize = 42;
-const TALENT_FROST_CHANNELING: usize = 43;
-const TALENT_SHATTER: usize = 44;
-const TALENT_ICE_BLOCK: usize = 45;
-const TALENT_IMP_CONE_OF_COLD: usize = 46;
-const TALENT_WINTERS_CHILL: usize = 47;
-const TALENT_ICE_BARRIER: usize = 48;
+pub(crate) const TALENT_ARCANE_SUBTLETY: usize = 0;
+pub(crate) const TALENT_ARCANE_FOCUS: usize = 1;
+pub(crate) const TALENT_IMP_ARCANE_MISSILES: usize = 2;
+pub(crate) const TALENT_WAND_SPEC: usize = 3;
+pub(crate) const TALENT_MAGIC_ABSORPTION: usize = 4;
+pub(crate) const TALENT_ARCANE_CONCENTRATION: usize = 5;
+pub(crate) const TALENT_MAGIC_ATTUNEMENT: usize = 6;
+pub(crate) const TALENT_IMP_ARCANE_EXPLOSION: usize = 7;
+pub(crate) const TALENT_ARCANE_RESILIENCE: usize = 8;
+pub(crate) const TALENT_IMP_MANA_SHIELD: usize = 9;
+pub(crate) const TALENT_IMP_COUNTERSPELL: usize = 10;
+pub(crate) const TALENT_ARCANE_MEDITATION: usize = 11;
+pub(crate) const TALENT_PRESENCE_OF_MIND: usize = 12;
+pub(crate) const TALENT_ARCANE_MIND: usize = 13;
+pub(crate) const TALENT_ARCANE_INSTABILITY: usize = 14;
+pub(crate) const TALENT_ARCANE_POWER: usize = 15;
+
+pub(crate) const TALENT_IMP_FIREBALL: usize = 16;
+pub(crate) const TALENT_IMPACT: usize = 17;
+pub(crate) const TALENT_IGNITE: usize = 18;
+pub(crate) const TALENT_FLAME_THROWING: usize = 19;
+pub(crate) const TALENT_IMP_FIRE_BLAST: usize = 20;
+pub(crate) const TALENT_INCINERATE: usize = 21;
+pub(crate) const TALENT_IMP_FLAMESTRIKE: usize = 22;
+pub(crate) const TALENT_PYROBLAST: usize = 23;
+pub(crate) const TALENT_BURNING_SOUL: usize = 24;
+pub(crate) const TALENT_IMP_SCORCH: usize = 25;
+pub(crate) const TALENT_IMP_FIRE_WARD: usize = 26;
+pub(crate) const TALENT_MASTER_OF_ELEMENTS: usize = 27;
+pub(crate) const TALENT_CRITICAL_MASS: usize = 28;
+pub(crate) const TALENT_BLAST_WAVE: usize = 29;
+pub(crate) const TALENT_FIRE_POWER: usize = 30;
+pub(crate) const TALENT_COMBUSTION: usize = 31;
+
+pub(crate) const TALENT_FROST_WARDING: usize = 32;
+pub(crate) const TALENT_IMP_FROSTBOLT: usize = 33;
+pub(crate) const TALENT_ELEMENTAL_PRECISION: usize = 34;
+pub(crate) const TALENT_ICE_SHARDS: usize = 35;
+pub(crate) const TALENT_FROSTBITE: usize = 36;
+pub(crate) const TALENT_IMP_FROST_NOVA: usize = 37;
+pub(crate) const TALENT_PERMAFROST: usize = 38;
+pub(crate) const TALENT_PIERCING_ICE: usize = 39;
+pub(crate) const TALENT_COLD_SNAP: usize = 40;
+pub(crate) const TALENT_IMP_BLIZZARD: usize = 41;
+pub(crate) const TALENT_ARCTIC_REACH: usize = 42;
+pub(crate) const TALENT_FROST_CHANNELING: usize = 43;
+pub(crate) const TALENT_SHATTER: usize = 44;
+pub(crate) const TALENT_ICE_BLOCK: usize = 45;
+pub(crate) const TALENT_IMP_CONE_OF_COLD: usize = 46;
+pub(crate) const TALENT_WINTERS_CHILL: usize = 47;
+pub(crate) const TALENT_ICE_BARRIER: usize = 48;
+
+// How deep apl lists can call each other
+const APL_MAX_DEPTH: u8 = 10;
 
 pub struct Mage {
     pub id: i32,
@@ -84,10 +91,13 @@ pub struct Mage {
     pub stats: Stats,
     pub auras: aura::Auras,
     pub cooldowns: cooldown::Cooldowns,
-    pub rng: ChaCha8Rng,
+    pub rngs: Rngs,
     apl_sequence: VecDeque<apl::AplAction>,
+    target_id: i32,
+    target_cycle: usize,
     combustion: i32,
     mana_gems: i32,
+    buffs: Vec<buff::Buff>,
 }
 
 impl Mage {
@@ -104,10 +114,13 @@ impl Mage {
             stats: Stats::default(),
             auras: aura::Auras::default(),
             cooldowns: cooldown::Cooldowns::default(),
-            rng: ChaCha8Rng::from_entropy(),
+            rngs: Rngs::default(),
             apl_sequence: VecDeque::new(),
+            target_id: 1,
+            target_cycle: 0,
             combustion: 0,
             mana_gems: 0,
+            buffs: vec![],
         }
     }
 
@@ -124,7 +137,7 @@ impl Mage {
     }
 
     fn is_horde(&self) -> bool {
-        self.player_config().race == common::Race::Undead || self.player_config().race == common::Race::Troll
+        self.player_config().race.is_horde()
     }
 
     fn is_alliance(&self) -> bool {
@@ -139,26 +152,38 @@ impl Mage {
         self.player_config().items.contains(&item_id)
     }
 
+    // Stats from the config are final unless they are gear only
+    pub fn player_stats(&self) -> Stats {
+        let player = self.player_config();
+
+        buff::player_stats(player, &self.buffs, race_base_stats(player.race), 59.5)
+    }
+
+    // Berserking gives 10% haste at full health up to 30% at 40% health or below
+    fn berserking_haste(&self) -> f64 {
+        let missing = self.player_config().missing_health.clamp(0.0, 60.0);
+
+        10.0 + 20.0 * missing / 60.0
+    }
+
     fn apl_next_event(&mut self, t: f64, targets: &HashMap<i32, Target>) -> Event {
         let mut event = Event::new(EventType::None);
+        let mut target_type = apl::AplTargetType::None;
 
         // Pending action sequence
         while !self.apl_sequence.is_empty() {
             let action = self.apl_sequence.pop_front().unwrap();
             event = self.apl_action(&action, t, targets);
             if event.event_type != EventType::None {
+                self.apl_set_target(&action.target.target_type, &event);
                 return event;
             }
         }
 
         // Go through APL from the top
-        for apl_item in &self.player_config().apl.items {
-            if self.apl_check_condition(&apl_item.condition, t, targets) {
-                event = self.apl_action(&apl_item.action, t, targets);
-                if event.event_type != EventType::None {
-                    break;
-                }
-            }
+        if let Some((ev, tt)) = self.apl_items_event(&self.player_config().apl.items, t, targets, 0) {
+            event = ev;
+            target_type = tt;
         }
 
         // Action sequence
@@ -173,18 +198,109 @@ impl Mage {
                     let action = self.apl_sequence.pop_front().unwrap();
                     let ev = self.apl_action(&action, t, targets);
                     if ev.event_type != EventType::None {
+                        self.apl_set_target(&action.target.target_type, &ev);
                         return ev;
                     }
                 }
             }
         } else if event.event_type == EventType::None {
             return self.wait_event(0.1, String::from("APL: No available action"));
+        } else {
+            self.apl_set_target(&target_type, &event);
         }
 
         event
     }
 
-    fn apl_action(&self, apl_action: &apl::AplAction, t: f64, targets: &HashMap<i32, Target>) -> Event {
+    // Returns the first available action in the items
+    // A RunList action stops the evaluation with an empty event even if nothing in the list was available
+    fn apl_items_event(&self, items: &[apl::AplItem], t: f64, targets: &HashMap<i32, Target>, depth: u8) -> Option<(Event, apl::AplTargetType)> {
+        if depth > APL_MAX_DEPTH {
+            return None;
+        }
+
+        for apl_item in items {
+            let target_id = self.apl_target(&apl_item.action, targets);
+            if !self.apl_check_condition(&apl_item.condition, t, targets, target_id) {
+                continue;
+            }
+
+            match apl_item.action.key {
+                apl::AplActionKey::CallList | apl::AplActionKey::RunList => {
+                    let result = self.apl_list(&apl_item.action.list).and_then(|list| self.apl_items_event(&list.items, t, targets, depth + 1));
+                    if result.is_some() {
+                        return result;
+                    }
+                    if apl_item.action.key == apl::AplActionKey::RunList {
+                        return Some((Event::new(EventType::None), apl::AplTargetType::None));
+                    }
+                }
+                _ => {
+                    let event = self.apl_action(&apl_item.action, t, targets);
+                    if event.event_type != EventType::None {
+                        return Some((event, apl_item.action.target.target_type.clone()));
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    // Player lists take precedence over lists shared in the config
+    pub(crate) fn apl_list(&self, name: &str) -> Option<&apl::AplList> {
+        self.player_config().apl.lists.iter().find(|list| list.name == name)
+            .or_else(|| self.config.as_ref().unwrap().apl_lists.iter().find(|list| list.name == name))
+    }
+
+    // Remember the target of the chosen action
+    fn apl_set_target(&mut self, target_type: &apl::AplTargetType, event: &Event) {
+        if event.target_id != 0 {
+            self.target_id = event.target_id;
+            if *target_type == apl::AplTargetType::Cycle {
+                self.target_cycle+= 1;
+            }
+        }
+    }
+
+    fn apl_target(&self, apl_action: &apl::AplAction, targets: &HashMap<i32, Target>) -> i32 {
+        let mut ids: Vec<i32> = targets.keys().copied().collect();
+        ids.sort();
+
+        if ids.is_empty() {
+            return apl_action.target_id;
+        }
+
+        let vint = apl_action.target.vint;
+
+        match apl_action.target.target_type {
+            apl::AplTargetType::None => {
+                apl_action.target_id
+            }
+            apl::AplTargetType::Current => {
+                self.target_id
+            }
+            apl::AplTargetType::LowestAuraStacks => {
+                *ids.iter().min_by_key(|id| targets[id].auras.stacks(vint, self.id)).unwrap()
+            }
+            apl::AplTargetType::MissingAura => {
+                *ids.iter().find(|id| !targets[id].auras.has_own(vint, self.id)).unwrap_or(&self.target_id)
+            }
+            apl::AplTargetType::HighestHealth => {
+                // Reverse so that the lowest id wins ties
+                *ids.iter().rev().max_by(|a, b| targets[a].health().total_cmp(&targets[b].health())).unwrap()
+            }
+            apl::AplTargetType::Cycle => {
+                ids[self.target_cycle % ids.len()]
+            }
+            apl::AplTargetType::Newest => {
+                // Targets are spawned in order, so the newest has the highest id
+                *ids.last().unwrap()
+            }
+        }
+    }
+
+    pub(crate) fn apl_action(&self, apl_action: &apl::AplAction, t: f64, targets: &HashMap<i32, Target>) -> Event {
         let mut event = Event::new(EventType::CastStart);
         match apl_action.key {
             apl::AplActionKey::Sequence => {
@@ -199,8 +315,11 @@ impl Mage {
                 event.t = 1.0;
                 return event
             }
+            apl::AplActionKey::ArcaneExplosion => {
+                event.spell = Some(self.this_spell(spell::arcane_explosion()));
+            }
             apl::AplActionKey::ArcaneMissiles => {
-                event.spell = Some(self.this_spell(spell::arcane_missiles()));
+                event.spell = Some(self.this_spell(spell::arcane_missiles_ranked(apl_action.rank)));
             }
             apl::AplActionKey::ArcanePotency => {
                 if !self.cooldowns.has(spell::ARCANE_POTENCY) && !self.cooldowns.has(cooldown::TRINKET_POWER) && self.has_item(item::TRINKET_HAZZARAH) {
@@ -217,6 +336,11 @@ impl Mage {
                     event.spell = Some(self.this_spell(spell::berserking()));
                 }
             }
+            apl::AplActionKey::BlastWave => {
+                if !self.cooldowns.has(spell::BLAST_WAVE) && self.talent(TALENT_BLAST_WAVE) > 0 {
+                    event.spell = Some(self.this_spell(spell::blast_wave()));
+                }
+            }
             apl::AplActionKey::BurstOfKnowledge => {
                 if !self.cooldowns.has(spell::BURST_OF_KNOWLEDGE) && self.has_item(item::TRINKET_BURST_OF_KNOWLEDGE) {
                     event.spell = Some(self.this_spell(spell::burst_of_knowledge()));
@@ -247,6 +371,11 @@ impl Mage {
                     event.spell = Some(self.this_spell(spell::combustion()));
                 }
             }
+            apl::AplActionKey::ConeOfCold => {
+                if !self.cooldowns.has(spell::CONE_OF_COLD) {
+                    event.spell = Some(self.this_spell(spell::cone_of_cold()));
+                }
+            }
             apl::AplActionKey::EphemeralPower => {
                 if !self.cooldowns.has(spell::EPHEMERAL_POWER) && !self.cooldowns.has(cooldown::TRINKET_POWER) && self.has_item(item::TRINKET_TOEP) {
                     event.spell = Some(self.this_spell(spell::ephemeral_power()));
@@ -263,18 +392,33 @@ impl Mage {
                 }
             }
             apl::AplActionKey::Fireball => {
-                event.spell = Some(self.this_spell(spell::fireball()));
+                event.spell = Some(self.this_spell(spell::fireball_ranked(apl_action.rank)));
             }
             apl::AplActionKey::FireBlast => {
                 if !self.cooldowns.has(spell::FIRE_BLAST) {
-                    event.spell = Some(self.this_spell(spell::fire_blast()));
+                    event.spell = Some(self.this_spell(spell::fire_blast_ranked(apl_action.rank)));
                 }
             }
+            apl::AplActionKey::Flamestrike => {
+                event.spell = Some(self.this_spell(spell::flamestrike()));
+            }
             apl::AplActionKey::Frostbolt => {
-                event.spell = Some(self.this_spell(spell::frostbolt()));
+                event.spell = Some(self.this_spell(spell::frostbolt_ranked(apl_action.rank)));
+            }
+            apl::AplActionKey::FrostNova => {
+                if !self.cooldowns.has(spell::FROST_NOVA) {
+                    event.spell = Some(self.this_spell(spell::frost_nova()));
+                }
             }
-            apl::AplActionKey::Innervate => {
-                event.spell = Some(self.this_spell(spell::innervate()));
+            apl::AplActionKey::IceBarrier => {
+                if !self.cooldowns.has(spell::ICE_BARRIER) && self.talent(TALENT_ICE_BARRIER) > 0 {
+                    event.spell = Some(self.this_spell(spell::ice_barrier()));
+                }
+            }
+            apl::AplActionKey::IceBlock => {
+                if !self.cooldowns.has(spell::ICE_BLOCK) && self.talent(TALENT_ICE_BLOCK) > 0 {
+                    event.spell = Some(self.this_spell(spell::ice_block()));
+                }
             }
             apl::AplActionKey::ManaGem => {
                 if !self.cooldowns.has(spell::MANA_GEM) {
@@ -291,11 +435,6 @@ impl Mage {
                     event.spell = Some(self.this_spell(spell::mana_potion()));
                 }
             }
-            apl::AplActionKey::ManaTide => {
-                if self.is_horde() {
-                    event.spell = Some(self.this_spell(spell::mana_tide()));
-                }
-            }
             apl::AplActionKey::MindQuickening => {
                 if !self.cooldowns.has(spell::MIND_QUICKENING) && !self.cooldowns.has(cooldown::TRINKET_POWER) && self.has_item(item::TRINKET_MQG) {
                     event.spell = Some(self.this_spell(spell::mind_quickening()));
@@ -311,9 +450,6 @@ impl Mage {
                     event.spell = Some(self.this_spell(spell::obsidian_insight()));
                 }
             }
-            apl::AplActionKey::PowerInfusion => {
-                event.spell = Some(self.this_spell(spell::power_infusion()));
-            }
             apl::AplActionKey::PresenceOfMind => {
                 if !self.cooldowns.has(spell::PRESENCE_OF_MIND) && self.talent(TALENT_PRESENCE_OF_MIND) > 0 {
                     event.spell = Some(self.this_spell(spell::presence_of_mind()));
@@ -321,7 +457,7 @@ impl Mage {
             }
             apl::AplActionKey::Pyroblast => {
                 if self.talent(TALENT_PYROBLAST) > 0 {
-                    event.spell = Some(self.this_spell(spell::pyroblast()));
+                    event.spell = Some(self.this_spell(spell::pyroblast_ranked(apl_action.rank)));
                 }
             }
             apl::AplActionKey::RobeArchmage => {
@@ -330,30 +466,36 @@ impl Mage {
                 }
             }
             apl::AplActionKey::Scorch => {
-                event.spell = Some(self.this_spell(spell::scorch()));
+                event.spell = Some(self.this_spell(spell::scorch_ranked(apl_action.rank)));
             }
             apl::AplActionKey::UnstablePower => {
                 if !self.cooldowns.has(spell::UNSTABLE_POWER) && !self.cooldowns.has(cooldown::TRINKET_POWER) && self.has_item(item::TRINKET_ZHC) {
                     event.spell = Some(self.this_spell(spell::unstable_power()));
                 }
             }
-            apl::AplActionKey::None => {
+            // Handed out by support units
+            apl::AplActionKey::Innervate | apl::AplActionKey::ManaTide | apl::AplActionKey::PowerInfusion => {
+                return Event::new(EventType::None);
+            }
+            apl::AplActionKey::CallList | apl::AplActionKey::RunList | apl::AplActionKey::None => {
                 return Event::new(EventType::None);
             }
         }
-        if event.spell.is_none() {
+        if let Some(spell) = &event.spell {
+            if spell.min_dmg > 0.0 {
+                event.target_id = self.apl_target(apl_action, targets);
+            }
+        } else {
             event.event_type = EventType::None;
-        } else if event.spell.as_ref().unwrap().min_dmg > 0.0 {
-            event.target_id = apl_action.target_id;
         }
         event
     }
 
-    fn apl_check_condition(&self, apl_condition: &apl::AplCondition, t: f64, targets: &HashMap<i32, Target>) -> bool {
+    fn apl_check_condition(&self, apl_condition: &apl::AplCondition, t: f64, targets: &HashMap<i32, Target>, target_id: i32) -> bool {
         match apl_condition.condition_type {
             apl::AplConditionType::And => {
                 for condition in &apl_condition.conditions {
-                    if !self.apl_check_condition(condition, t, targets) {
+                    if !self.apl_check_condition(condition, t, targets, target_id) {
                         return false;
                     }
                 }
@@ -361,7 +503,7 @@ impl Mage {
             }
             apl::AplConditionType::Or => {
                 for condition in &apl_condition.conditions {
-                    if self.apl_check_condition(condition, t, targets) {
+                    if self.apl_check_condition(condition, t, targets, target_id) {
                         return true;
                     }
                 }
@@ -372,8 +514,8 @@ impl Mage {
                     return false;
                 }
 
-                let a = self.apl_value(&apl_condition.values[0], t, targets);
-                let b = self.apl_value(&apl_condition.values[1], t, targets);
+                let a = self.apl_value(&apl_condition.values[0], t, targets, target_id);
+                let b = self.apl_value(&apl_condition.values[1], t, targets, target_id);
 
                 if apl_condition.op == apl::AplConditionOp::Eq {
                     return a == b;
@@ -396,19 +538,19 @@ impl Mage {
                     return false;
                 }
 
-                self.apl_check_condition(&apl_condition.conditions[0], t, targets)
+                !self.apl_check_condition(&apl_condition.conditions[0], t, targets, target_id)
             }
             apl::AplConditionType::False => {
                 if apl_condition.values.len() != 1 {
                     return false;
                 }
-                self.apl_value(&apl_condition.values[0], t, targets) == 0.0
+                self.apl_value(&apl_condition.values[0], t, targets, target_id) == 0.0
             }
             apl::AplConditionType::True => {
                 if apl_condition.values.len() != 1 {
                     return false;
                 }
-                self.apl_value(&apl_condition.values[0], t, targets) == 1.0
+                self.apl_value(&apl_condition.values[0], t, targets, target_id) == 1.0
             }
             apl::AplConditionType::None => {
                 true
@@ -416,7 +558,7 @@ impl Mage {
         }
     }
 
-    fn apl_value(&self, apl_value: &apl::AplValue, t: f64, targets: &HashMap<i32, Target>) -> f64 {
+    fn apl_value(&self, apl_value: &apl::AplValue, t: f64, targets: &HashMap<i32, Target>, target_id: i32) -> f64 {
         match apl_value.value_type {
             apl::AplValueType::Const => {
                 apl_value.vfloat
@@ -434,58 +576,50 @@ impl Mage {
                 self.talent(apl_value.vint as usize) as f64
             }
             apl::AplValueType::PlayerCooldownExists => {
-                if self.cooldowns.has(apl_value.vint) { 1.0 } else { 0.0 }
+                self.cooldowns.has(apl_value.vint) as u8 as f64
             }
             apl::AplValueType::PlayerCooldownReact => {
                 // TODO: Reaction time
-                if self.cooldowns.has(apl_value.vint) { 1.0 } else { 0.0 }
+                self.cooldowns.has(apl_value.vint) as u8 as f64
             }
             apl::AplValueType::PlayerCooldownDuration => {
-                if self.cooldowns.has(apl_value.vint) {
-                    self.cooldowns.cooldowns.get(&apl_value.vint).unwrap().t_expires - t
-                } else {
-                    0.0
-                }
+                self.cooldowns.cooldowns.get(&apl_value.vint).map_or(0.0, |cooldown| cooldown.t_expires - t)
             }
             apl::AplValueType::PlayerAuraExists => {
-                if self.auras.has(apl_value.vint, self.id()) { 1.0 } else { 0.0 }
+                self.auras.has(apl_value.vint, self.id()) as u8 as f64
             }
             apl::AplValueType::PlayerAuraReact => {
-                if self.auras.can_react_any(apl_value.vint, t - self.reaction_time()) { 1.0 } else { 0.0 }
+                self.auras.can_react_any(apl_value.vint, t - self.reaction_time()) as u8 as f64
             }
             apl::AplValueType::PlayerAuraStacks => {
                 self.auras.stacks(apl_value.vint, self.id()) as f64
             }
             apl::AplValueType::PlayerAuraDuration => {
-                if self.auras.has(apl_value.vint, self.id()) {
-                    self.auras.get_aura(apl_value.vint, self.id()).unwrap().t_expires - t
-                } else {
-                    0.0
-                }
+                self.auras.get_aura(apl_value.vint, self.id()).map_or(0.0, |aura| aura.t_expires - t)
             }
             apl::AplValueType::TargetAuraExists => {
-                if let Some(target) = targets.get(&1) {
+                if let Some(target) = targets.get(&target_id) {
                     if target.auras.has(apl_value.vint, self.id()) { 1.0 } else { 0.0 }
                 } else {
                     0.0
                 }
             }
             apl::AplValueType::TargetAuraReact => {
-                if let Some(target) = targets.get(&1) {
+                if let Some(target) = targets.get(&target_id) {
                     if target.auras.can_react(apl_value.vint, self.id(), t - self.reaction_time()) { 1.0 } else { 0.0 }
                 } else {
                     0.0
                 }
             }
             apl::AplValueType::TargetAuraStacks => {
-                if let Some(target) = targets.get(&1) {
+                if let Some(target) = targets.get(&target_id) {
                     target.auras.stacks(apl_value.vint, self.id()) as f64
                 } else {
                     0.0
                 }
             }
             apl::AplValueType::TargetAuraDuration => {
-                if let Some(target) = targets.get(&1) {
+                if let Some(target) = targets.get(&target_id) {
                     if target.auras.has(apl_value.vint, self.id()) {
                         target.auras.get_aura(apl_value.vint, self.id()).unwrap().t_expires - t
                     } else {
@@ -535,6 +669,20 @@ impl Mage {
     }
 }
 
+// Level 60 mage base stats
+fn race_base_stats(race: common::Race) -> Stats {
+    let (int, spi) = match race {
+        common::Race::Gnome => (132.0, 120.0),
+        common::Race::Human => (125.0, 126.0),
+        common::Race::Troll => (121.0, 121.0),
+        common::Race::Undead => (123.0, 125.0),
+        // Not a mage race
+        _ => (0.0, 0.0),
+    };
+
+    Stats { int, spi, crit: 0.2, ..Default::default() }
+}
+
 impl Unit for Mage {
     fn id(&self) -> i32 {
         self.id
@@ -552,14 +700,11 @@ impl Unit for Mage {
 
     fn set_config(&mut self, config: Config) {
         self.config = Some(config);
+        self.buffs = buff::active_buffs(&self.player_config().buffs, self.player_config().race);
     }
 
     fn new_rng(&mut self, rng_seed: u64) {
-        if rng_seed == 0 {
-            self.rng = ChaCha8Rng::from_entropy();
-        } else {
-            self.rng = ChaCha8Rng::seed_from_u64(rng_seed);
-        }
+        self.rngs = Rngs::new(rng_seed, Owner::Unit);
     }
     
     fn level(&self) -> i32 {
@@ -568,7 +713,9 @@ impl Unit for Mage {
 
     fn max_mana(&self) -> f64 {
         // Subtract 280 because the first 20 intellect only gives 1 mana instead of 15
-        self.base_mana + self.stats.int * 15.0 - 280.0 + self.stats.mana
+        let mana = self.base_mana + self.stats.int * 15.0 - 280.0 + self.stats.mana;
+
+        mana * (1.0 + 0.02 * self.talent(TALENT_ARCANE_MIND) as f64)
     }
 
     fn current_mana(&self) -> f64 {
@@ -663,7 +810,13 @@ impl Unit for Mage {
     }
 
     fn spell_penetration(&self, school: School) -> f64 {
-        self.stats.spell_penetration + self.auras.stats.spell_penetration
+        let mut pen = self.stats.spell_penetration + self.auras.stats.spell_penetration;
+
+        if self.talent(TALENT_ARCANE_SUBTLETY) > 0 {
+            pen+= 5.0 * (self.talent(TALENT_ARCANE_SUBTLETY) as f64);
+        }
+
+        pen
     }
 
     fn spell_mana_cost(&self, spell: &spell::Spell) -> f64 {
@@ -680,6 +833,9 @@ impl Unit for Mage {
         }
 
         // Multipliers
+        if spell.school == School::Frost && self.talent(TALENT_FROST_CHANNELING) > 0 {
+            cost*= 1.0 - 0.05 * (self.talent(TALENT_FROST_CHANNELING) as f64);
+        }
         if self.auras.has_any(aura::ARCANE_POWER) {
             cost*= 1.3;
         }
@@ -707,6 +863,12 @@ impl Unit for Mage {
             return crit;
         }
 
+        if self.talent(TALENT_IMP_ARCANE_EXPLOSION) > 0 && spell.id == spell::ARCANE_EXPLOSION {
+            crit+= 2.0 * self.talent(TALENT_IMP_ARCANE_EXPLOSION) as f64;
+        }
+        if self.talent(TALENT_IMP_FLAMESTRIKE) > 0 && spell.id == spell::FLAMESTRIKE {
+            crit+= 5.0 * self.talent(TALENT_IMP_FLAMESTRIKE) as f64;
+        }
         if self.talent(TALENT_INCINERATE) > 0 && (spell.id == spell::FIRE_BLAST || spell.id == spell::SCORCH) {
             crit+= 2.0 * self.talent(TALENT_INCINERATE) as f64;
         }
@@ -726,6 +888,14 @@ impl Unit for Mage {
         crit
     }
 
+    fn spell_crit_chance_frozen(&self, spell: &spell::Spell) -> f64 {
+        if self.talent(TALENT_SHATTER) > 0 {
+            return 10.0 * self.talent(TALENT_SHATTER) as f64;
+        }
+
+        0.0
+    }
+
     fn spell_crit_dmg_multiplier(&self, spell: &spell::Spell) -> f64 {
         let mut multi = 1.0;
 
@@ -743,15 +913,7 @@ impl Unit for Mage {
         let mut dmg = 1.0;
         let mut additive = 1.0;
 
-        if self.player_config().dmf_dmg {
-            dmg*= 1.1;
-        }
-        if self.player_config().soul_revival {
-            dmg*= 1.1;
-        }
-        if self.player_config().traces_of_silithyst {
-            dmg*= 1.05;
-        }
+        dmg*= buff::dmg_multiplier(self.player_config(), &self.buffs);
         if self.auras.has_any(aura::POWER_INFUSION) && !self.auras.has_any(aura::ARCANE_POWER) {
             dmg*= 1.2;
         }
@@ -763,6 +925,9 @@ impl Unit for Mage {
         if spell.school == School::Frost && self.talent(TALENT_PIERCING_ICE) > 0 {
             additive+= 0.02 * (self.talent(TALENT_PIERCING_ICE) as f64);
         }
+        if spell.id == spell::CONE_OF_COLD && self.talent(TALENT_IMP_CONE_OF_COLD) > 0 {
+            additive+= 0.05 + 0.1 * (self.talent(TALENT_IMP_CONE_OF_COLD) as f64);
+        }
         if self.auras.has_any(aura::ARCANE_POWER) {
             additive+= 0.3;
         }
@@ -806,7 +971,7 @@ impl Unit for Mage {
             haste*= 1.33;
         }
         if self.auras.has_any(aura::BERSERKING) {
-            haste*= 1.1;
+            haste*= 1.0 + self.berserking_haste() / 100.0;
         }
 
         1.0 / haste
@@ -820,6 +985,9 @@ impl Unit for Mage {
         if spell.id == spell::FIRE_BLAST && self.talent(TALENT_IMP_FIRE_BLAST) > 0 {
             return -0.5 * (self.talent(TALENT_IMP_FIRE_BLAST) as f64);
         }
+        if spell.id == spell::FROST_NOVA && self.talent(TALENT_IMP_FROST_NOVA) > 0 {
+            return -2.0 * (self.talent(TALENT_IMP_FROST_NOVA) as f64);
+        }
         if spell.id == spell::EVOCATION && self.has_set(item::SET_T3, 2) {
             return -60.0;
         }
@@ -827,6 +995,33 @@ impl Unit for Mage {
         0.0
     }
 
+    fn spell_range_mod(&self, spell: &spell::Spell) -> f64 {
+        if spell.school == School::Fire && self.talent(TALENT_FLAME_THROWING) > 0 {
+            return 3.0 * (self.talent(TALENT_FLAME_THROWING) as f64);
+        }
+        if spell.id == spell::FROSTBOLT && self.talent(TALENT_ARCTIC_REACH) > 0 {
+            return spell.range * 0.1 * (self.talent(TALENT_ARCTIC_REACH) as f64);
+        }
+
+        0.0
+    }
+
+    fn spell_threat_multiplier(&self, spell: &spell::Spell) -> f64 {
+        let mut threat = 1.0;
+
+        if spell.school == School::Arcane && self.talent(TALENT_ARCANE_SUBTLETY) > 0 {
+            threat-= 0.2 * (self.talent(TALENT_ARCANE_SUBTLETY) as f64);
+        }
+        if spell.school == School::Fire && self.talent(TALENT_BURNING_SOUL) > 0 {
+            threat-= 0.15 * (self.talent(TALENT_BURNING_SOUL) as f64);
+        }
+        if spell.school == School::Frost && self.talent(TALENT_FROST_CHANNELING) > 0 {
+            threat-= 0.1 * (self.talent(TALENT_FROST_CHANNELING) as f64);
+        }
+
+        threat
+    }
+
     fn auras(&mut self) -> &mut aura::Auras {
         &mut self.auras
     }
@@ -840,8 +1035,7 @@ impl Unit for Mage {
 
         match event.event_type {
             EventType::CastSuccess => {
-                if event.spell.is_some() {
-                    let spell = event.spell.as_ref().unwrap();
+                if let Some(spell) = &event.spell {
                     let is_harmful = spell.min_dmg > 0.0 || spell.max_dmg > 0.0;
 
                     // Item triggers
@@ -850,16 +1044,16 @@ impl Unit for Mage {
                             // Mana gems
                             spell::MANA_GEM => {
                                 if self.mana_gems == 0 {
-                                    let fval = self.rng.gen_range(1000..=1200) as f64;
+                                    let fval = self.rngs.get(Stream::Consumable, self.id).gen_range(1000..=1200) as f64;
                                     events.push(self.mana_event(fval, String::from("Mana Ruby")));
                                 } else if self.mana_gems == 1 {
-                                    let fval = self.rng.gen_range(775..=925) as f64;
+                                    let fval = self.rngs.get(Stream::Consumable, self.id).gen_range(775..=925) as f64;
                                     events.push(self.mana_event(fval, String::from("Mana Citrine")));
                                 } else if self.mana_gems == 2 {
-                                    let fval = self.rng.gen_range(550..=650) as f64;
+                                    let fval = self.rngs.get(Stream::Consumable, self.id).gen_range(550..=650) as f64;
                                     events.push(self.mana_event(fval, String::from("Mana Jade")));
                                 } else if self.mana_gems == 3 {
-                                    let fval = self.rng.gen_range(375..=425) as f64;
+                                    let fval = self.rngs.get(Stream::Consumable, self.id).gen_range(375..=425) as f64;
                                     events.push(self.mana_event(fval, String::from("Mana Agate")));
                                 }
                                 self.mana_gems+= 1;
@@ -868,7 +1062,7 @@ impl Unit for Mage {
 
                             // Mana potions
                             spell::MANA_POTION => {
-                                let mut fval = self.rng.gen_range(1350..=2250) as f64;
+                                let mut fval = self.rngs.get(Stream::Consumable, self.id).gen_range(1350..=2250) as f64;
                                 if self.has_item(item::TRINKET_ALCHEMIST_STONE) {
                                     fval*= 1.33;
                                 }
@@ -882,7 +1076,7 @@ impl Unit for Mage {
                                 return events;
                             }
                             spell::CHAOS_FIRE => {
-                                let fval = self.rng.gen_range(1..=500) as f64;
+                                let fval = self.rngs.get(Stream::Consumable, self.id).gen_range(1..=500) as f64;
                                 events.push(self.mana_event(fval, String::from("Fire Ruby")));
                                 events.push(self.aura_event(aura::chaos_fire(), 0));
                                 events.push(self.cooldown_event(cooldown::shared_trinket_power(events.last().as_ref().unwrap().aura.as_ref().unwrap().duration)));
@@ -929,12 +1123,12 @@ impl Unit for Mage {
 
                             // Other items
                             spell::CELESTIAL_ORB => {
-                                let fval = self.rng.gen_range(400..=1200) as f64;
+                                let fval = self.rngs.get(Stream::Consumable, self.id).gen_range(400..=1200) as f64;
                                 events.push(self.mana_event(fval, spell.name.clone()));
                                 return events;
                             }
                             spell::ROBE_ARCHMAGE => {
-                                let fval = self.rng.gen_range(375..=625) as f64;
+                                let fval = self.rngs.get(Stream::Consumable, self.id).gen_range(375..=625) as f64;
                                 events.push(self.mana_event(fval, spell.name.clone()));
                                 return events;
                             }
@@ -951,28 +1145,25 @@ impl Unit for Mage {
                             spell::ARCANE_POWER => {
                                 events.push(self.aura_event(aura::arcane_power(), 0));
                             }
-                            // spell::COLD_SNAP => {
-                                // No spells worth resetting lmao
-                            // }
+                            spell::BERSERKING => {
+                                events.push(self.aura_event(aura::berserking(), 0));
+                            }
+                            spell::COLD_SNAP => {
+                                // Reset all frost cooldowns except cold snap itself
+                                let mut cooldowns: Vec<&cooldown::Cooldown> = self.cooldowns.cooldowns.values()
+                                    .filter(|cooldown| cooldown.school == School::Frost && cooldown.id != spell::COLD_SNAP)
+                                    .collect();
+                                cooldowns.sort_by_key(|cooldown| cooldown.id);
+                                for cooldown in cooldowns {
+                                    events.push(self.cooldown_expire_event(cooldown.id, cooldown.name.clone()));
+                                }
+                            }
                             spell::COMBUSTION => {
                                 events.push(self.aura_event(aura::combustion(), 0));
                             }
                             spell::EVOCATION => {
                                 events.push(self.aura_event(aura::evocation(), 0));
                             }
-                            spell::INNERVATE => {
-                                events.push(self.aura_event(aura::innervate(), 0));
-                            }
-                            spell::MANA_TIDE => {
-                                events.push(self.mana_event_at(290.0, String::from("Mana Tide"), 3.0));
-                                events.push(self.mana_event_at(290.0, String::from("Mana Tide"), 6.0));
-                                events.push(self.mana_event_at(290.0, String::from("Mana Tide"), 9.0));
-                                events.push(self.mana_event_at(290.0, String::from("Mana Tide"), 12.0));
-                                events.push(self.aura_event(aura::mana_tide(), 0));
-                            }
-                            spell::POWER_INFUSION => {
-                                events.push(self.aura_event(aura::power_infusion(), 0));
-                            }
                             spell::PRESENCE_OF_MIND => {
                                 events.push(self.aura_event(aura::presence_of_mind(), 0));
                             }
@@ -980,7 +1171,7 @@ impl Unit for Mage {
                         }
                     }
 
-                    if self.has_set(item::SET_T2, 8) && (spell.id == spell::ARCANE_MISSILES || spell.id == spell::FIREBALL || spell.id == spell::FROSTBOLT) && self.rng.gen_range(1..=10) == 1 {
+                    if self.has_set(item::SET_T2, 8) && (spell.id == spell::ARCANE_MISSILES || spell.id == spell::FIREBALL || spell.id == spell::FROSTBOLT) && self.rngs.get(Stream::Proc, self.id).gen_range(1..=10) == 1 {
                         events.push(self.aura_event(aura::netherwind_focus(), 0));
                     }
 
@@ -997,7 +1188,7 @@ impl Unit for Mage {
                         events.push(self.aura_expire_event(aura::clearcast(), 0));
                     }
 
-                    if spell.can_proc && self.has_item(item::TRINKET_BLUE_DRAGON) && self.rng.gen_range(1..=50) == 1 {
+                    if spell.can_proc && self.has_item(item::TRINKET_BLUE_DRAGON) && self.rngs.get(Stream::Proc, self.id).gen_range(1..=50) == 1 {
                         events.push(self.aura_event(aura::blue_dragon(), 0));
                     }
 
@@ -1013,8 +1204,7 @@ impl Unit for Mage {
             }
 
             EventType::SpellImpact => {
-                if event.spell_instance.is_some() {
-                    let instance = event.spell_instance.as_ref().unwrap();
+                if let Some(instance) = &event.spell_instance {
 
                     if instance.result == spell::SpellResult::Miss && self.has_set(item::SET_AQ40, 5) {
                         events.push(self.aura_event(aura::enigmas_answer(), 0));
@@ -1028,20 +1218,33 @@ impl Unit for Mage {
                         if instance.spell.id == spell::PYROBLAST {
                             events.push(self.spell_event(self.this_spell(spell::pyroblast_dot(instance.spell.rank)), event.target_id));
                         }
+                        if instance.spell.id == spell::FLAMESTRIKE {
+                            events.push(self.spell_event(self.this_spell(spell::flamestrike_dot()), event.target_id));
+                        }
                         if instance.spell.id == spell::FIRE_VULNERABILITY {
                             events.push(self.aura_event(aura::fire_vulnerability(), event.target_id));
                         }
 
+                        // Freezing effects, damage breaking the freeze is not modelled
+                        if self.config.as_ref().unwrap().target_freezable {
+                            if instance.spell.id == spell::FROST_NOVA {
+                                events.push(self.aura_event(aura::frost_nova(), event.target_id));
+                            }
+                            if (instance.spell.id == spell::FROSTBOLT || instance.spell.id == spell::CONE_OF_COLD) && self.talent(TALENT_FROSTBITE) > 0 && self.rngs.get(Stream::Proc, self.id).gen_range(0..100) < 5 * self.talent(TALENT_FROSTBITE) as i32 {
+                                events.push(self.aura_event(aura::frostbite(), event.target_id));
+                            }
+                        }
+
                         if instance.spell.id == spell::SCORCH && self.talent(TALENT_IMP_SCORCH) > 0 {
                             let imp_sc = self.talent(TALENT_IMP_SCORCH) as i32;
-                            if imp_sc == 3 || self.rng.gen_range(0..2) < imp_sc {
+                            if imp_sc == 3 || self.rngs.get(Stream::Proc, self.id).gen_range(0..2) < imp_sc {
                                 events.push(self.spell_event(self.this_spell(spell::fire_vulnerability()), event.target_id));
                             }
                         }
 
                         if !instance.spell.is_dot {
                             if self.talent(TALENT_ARCANE_CONCENTRATION) > 0 {
-                                let mut fval = self.rng.gen_range(0.0..=100.0);
+                                let mut fval = self.rngs.get(Stream::Proc, self.id).gen_range(0.0..=100.0);
                                 // Less chance per tick for channeled spells
                                 if instance.spell.ticks > 0 {
                                     fval/= instance.spell.ticks as f64;
@@ -1062,7 +1265,7 @@ impl Unit for Mage {
                                 }
                             }
 
-                            if self.talent(TALENT_WINTERS_CHILL) > 0 && instance.spell.school == School::Frost && (self.talent(TALENT_WINTERS_CHILL) == 5 || self.rng.gen_range(1..=5) <= self.talent(TALENT_WINTERS_CHILL) as i32) {
+                            if self.talent(TALENT_WINTERS_CHILL) > 0 && instance.spell.school == School::Frost && (self.talent(TALENT_WINTERS_CHILL) == 5 || self.rngs.get(Stream::Proc, self.id).gen_range(1..=5) <= self.talent(TALENT_WINTERS_CHILL) as i32) {
                                 events.push(self.aura_event(aura::winters_chill(), event.target_id));
                             }
                         }
@@ -1082,8 +1285,7 @@ impl Unit for Mage {
             }
 
             EventType::AuraGain => {
-                if event.aura.is_some() {
-                    let aura = event.aura.as_ref().unwrap();
+                if let Some(aura) = &event.aura {
 
                     if aura.id == aura::COMBUSTION {
                         self.combustion = 0;
@@ -1092,8 +1294,7 @@ impl Unit for Mage {
             }
 
             EventType::AuraExpire => {
-                if event.aura.is_some() {
-                    let aura = event.aura.as_ref().unwrap();
+                if let Some(aura) = &event.aura {
 
                     if aura.id == aura::COMBUSTION {
                         events.push(self.spell_cooldown_event(spell::combustion()));
@@ -1120,4 +1321,756 @@ impl Unit for Mage {
 
         self.apl_next_event(t, targets)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apl::{AplCondition, AplConditionOp, AplConditionType, AplValue, AplValueType};
+
+    fn test_config() -> Config {
+        let player = PlayerConfig {
+            name: String::from("Test"),
+            level: 60,
+            talents: vec![0; 49],
+            stats: Stats { int: 300.0, ..Default::default() },
+            ..Default::default()
+        };
+
+        Config {
+            duration: 120.0,
+            distance: 30,
+            reaction_time: 0.3,
+            target_level: 63,
+            targets: 1,
+            players: vec![player],
+            ..Default::default()
+        }
+    }
+
+    fn test_mage(config: Config) -> Mage {
+        let mut mage = Mage::new();
+        mage.stats = config.players[0].stats;
+        mage.set_config(config);
+        mage.reset();
+        mage
+    }
+
+    fn test_targets() -> HashMap<i32, Target> {
+        let mut targets = HashMap::new();
+        targets.insert(1, Target::new(1));
+        targets
+    }
+
+    // A mage and target with some state to evaluate the apl against at t = 10
+    fn test_state() -> (Mage, HashMap<i32, Target>) {
+        let mut config = test_config();
+        config.players[0].talents[TALENT_IGNITE] = 5;
+
+        let mut mage = test_mage(config);
+        mage.mana = 1000.0;
+
+        let mut cooldown = cooldown::Cooldown::new(spell::FIRE_BLAST, String::from("Fire Blast"), 8.0);
+        cooldown.t_gained = 6.0;
+        cooldown.t_expires = 14.0;
+        mage.cooldowns.add(cooldown);
+
+        let mut aura = aura::arcane_power();
+        aura.owner_id = mage.id;
+        aura.t_gained = 9.8;
+        aura.t_expires = 24.8;
+        mage.auras.add(aura);
+
+        let mut targets = test_targets();
+        let mut aura = aura::fire_vulnerability();
+        aura.owner_id = mage.id;
+        aura.t_gained = 5.0;
+        aura.t_expires = 35.0;
+        let target = targets.get_mut(&1).unwrap();
+        target.auras.add(aura.clone());
+        target.auras.add(aura.clone());
+        target.auras.add(aura);
+
+        (mage, targets)
+    }
+
+    fn value(value_type: AplValueType, vint: i32, vfloat: f64) -> AplValue {
+        AplValue { value_type, vint, vfloat, ..Default::default() }
+    }
+
+    fn constant(vfloat: f64) -> AplValue {
+        value(AplValueType::Const, 0, vfloat)
+    }
+
+    fn cmp(op: AplConditionOp, a: f64, b: f64) -> AplCondition {
+        AplCondition {
+            condition_type: AplConditionType::Cmp,
+            op,
+            values: vec![constant(a), constant(b)],
+            ..Default::default()
+        }
+    }
+
+    fn boolean(condition_type: AplConditionType, values: Vec<AplValue>) -> AplCondition {
+        AplCondition { condition_type, values, ..Default::default() }
+    }
+
+    fn group(condition_type: AplConditionType, conditions: Vec<AplCondition>) -> AplCondition {
+        AplCondition { condition_type, conditions, ..Default::default() }
+    }
+
+    fn always() -> AplCondition {
+        AplCondition::default()
+    }
+
+    fn never() -> AplCondition {
+        cmp(AplConditionOp::Eq, 0.0, 1.0)
+    }
+
+    #[test]
+    fn apl_values() {
+        let (mage, targets) = test_state();
+        let t = 10.0;
+        let table = vec![
+            (AplValueType::None, 0, 0.0, 0.0),
+            (AplValueType::Const, 0, 12.5, 12.5),
+            (AplValueType::PlayerMana, 0, 0.0, 1000.0),
+            (AplValueType::PlayerManaPercent, 0, 0.0, 1000.0 / mage.max_mana() * 100.0),
+            (AplValueType::PlayerManaDeficit, 0, 0.0, mage.max_mana() - 1000.0),
+            (AplValueType::PlayerTalentCount, TALENT_IGNITE as i32, 0.0, 5.0),
+            (AplValueType::PlayerTalentCount, TALENT_PYROBLAST as i32, 0.0, 0.0),
+            (AplValueType::PlayerCooldownExists, spell::FIRE_BLAST, 0.0, 1.0),
+            (AplValueType::PlayerCooldownExists, spell::EVOCATION, 0.0, 0.0),
+            (AplValueType::PlayerCooldownReact, spell::FIRE_BLAST, 0.0, 1.0),
+            (AplValueType::PlayerCooldownReact, spell::EVOCATION, 0.0, 0.0),
+            (AplValueType::PlayerCooldownDuration, spell::FIRE_BLAST, 0.0, 4.0),
+            (AplValueType::PlayerCooldownDuration, spell::EVOCATION, 0.0, 0.0),
+            (AplValueType::PlayerAuraExists, aura::ARCANE_POWER, 0.0, 1.0),
+            (AplValueType::PlayerAuraExists, aura::COMBUSTION, 0.0, 0.0),
+            // Gained 0.2s ago, which is less than the reaction time
+            (AplValueType::PlayerAuraReact, aura::ARCANE_POWER, 0.0, 0.0),
+            (AplValueType::PlayerAuraReact, aura::COMBUSTION, 0.0, 0.0),
+            (AplValueType::PlayerAuraStacks, aura::ARCANE_POWER, 0.0, 1.0),
+            (AplValueType::PlayerAuraStacks, aura::COMBUSTION, 0.0, 0.0),
+            (AplValueType::PlayerAuraDuration, aura::ARCANE_POWER, 0.0, 14.8),
+            (AplValueType::PlayerAuraDuration, aura::COMBUSTION, 0.0, 0.0),
+            (AplValueType::TargetAuraExists, aura::FIRE_VULNERABILITY, 0.0, 1.0),
+            (AplValueType::TargetAuraExists, aura::WINTERS_CHILL, 0.0, 0.0),
+            (AplValueType::TargetAuraReact, aura::FIRE_VULNERABILITY, 0.0, 1.0),
+            (AplValueType::TargetAuraReact, aura::WINTERS_CHILL, 0.0, 0.0),
+            (AplValueType::TargetAuraStacks, aura::FIRE_VULNERABILITY, 0.0, 3.0),
+            (AplValueType::TargetAuraStacks, aura::WINTERS_CHILL, 0.0, 0.0),
+            (AplValueType::TargetAuraDuration, aura::FIRE_VULNERABILITY, 0.0, 25.0),
+            (AplValueType::TargetAuraDuration, aura::WINTERS_CHILL, 0.0, 0.0),
+            // Spell values are not implemented yet
+            (AplValueType::SpellTravelTime, 0, 0.0, 0.0),
+            (AplValueType::SpellCastTime, 0, 0.0, 0.0),
+            (AplValueType::SpellTravelCastTime, 0, 0.0, 0.0),
+            (AplValueType::SpellManaCost, 0, 0.0, 0.0),
+            (AplValueType::SpellCanCast, 0, 0.0, 0.0),
+            (AplValueType::SimTime, 0, 0.0, 10.0),
+            (AplValueType::SimTimePercent, 0, 0.0, 10.0 / 120.0 * 100.0),
+            (AplValueType::SimDuration, 0, 0.0, 120.0),
+            (AplValueType::SimDistance, 0, 0.0, 30.0),
+            (AplValueType::SimReactionTime, 0, 0.0, 0.3),
+            (AplValueType::SimTargetLevel, 0, 0.0, 63.0),
+        ];
+
+        for (value_type, vint, vfloat, expected) in table {
+            let v = value(value_type.clone(), vint, vfloat);
+            let result = mage.apl_value(&v, t, &targets, 1);
+            assert!((result - expected).abs() < 1e-9, "{:?} ({}): expected {}, got {}", value_type, vint, expected, result);
+        }
+    }
+
+    #[test]
+    fn apl_values_without_target() {
+        let (mage, _) = test_state();
+        let targets = HashMap::new();
+
+        for value_type in [AplValueType::TargetAuraExists, AplValueType::TargetAuraReact, AplValueType::TargetAuraStacks, AplValueType::TargetAuraDuration] {
+            let v = value(value_type.clone(), aura::FIRE_VULNERABILITY, 0.0);
+            assert_eq!(mage.apl_value(&v, 10.0, &targets, 1), 0.0, "{:?}", value_type);
+        }
+    }
+
+    #[test]
+    fn apl_react_after_reaction_time() {
+        let (mage, targets) = test_state();
+
+        assert_eq!(mage.apl_value(&value(AplValueType::PlayerAuraReact, aura::ARCANE_POWER, 0.0), 10.2, &targets, 1), 1.0);
+        assert_eq!(mage.apl_value(&value(AplValueType::TargetAuraReact, aura::FIRE_VULNERABILITY, 0.0), 5.2, &targets, 1), 0.0);
+    }
+
+    #[test]
+    fn apl_condition_ops() {
+        let (mage, targets) = test_state();
+        let table = vec![
+            (AplConditionOp::None, 1.0, 1.0, false),
+            (AplConditionOp::Eq, 1.0, 1.0, true),
+            (AplConditionOp::Eq, 1.0, 2.0, false),
+            (AplConditionOp::Neq, 1.0, 2.0, true),
+            (AplConditionOp::Neq, 1.0, 1.0, false),
+            (AplConditionOp::Gt, 2.0, 1.0, true),
+            (AplConditionOp::Gt, 1.0, 1.0, false),
+            (AplConditionOp::Gte, 1.0, 1.0, true),
+            (AplConditionOp::Gte, 0.0, 1.0, false),
+            (AplConditionOp::Lt, 0.0, 1.0, true),
+            (AplConditionOp::Lt, 1.0, 1.0, false),
+            (AplConditionOp::Lte, 1.0, 1.0, true),
+            (AplConditionOp::Lte, 2.0, 1.0, false),
+        ];
+
+        for (op, a, b, expected) in table {
+            let condition = cmp(op.clone(), a, b);
+            assert_eq!(mage.apl_check_condition(&condition, 10.0, &targets, 1), expected, "{} {:?} {}", a, op, b);
+        }
+    }
+
+    #[test]
+    fn apl_condition_types() {
+        let (mage, targets) = test_state();
+        let mut bad_cmp = cmp(AplConditionOp::Eq, 1.0, 1.0);
+        bad_cmp.values.pop();
+
+        let table = vec![
+            ("none", always(), true),
+            ("and empty", group(AplConditionType::And, vec![]), true),
+            ("and all true", group(AplConditionType::And, vec![always(), always()]), true),
+            ("and one false", group(AplConditionType::And, vec![always(), never()]), false),
+            ("or empty", group(AplConditionType::Or, vec![]), false),
+            ("or one true", group(AplConditionType::Or, vec![never(), always()]), true),
+            ("or all false", group(AplConditionType::Or, vec![never(), never()]), false),
+            ("cmp wrong value count", bad_cmp, false),
+            ("not true", group(AplConditionType::Not, vec![always()]), false),
+            ("not false", group(AplConditionType::Not, vec![never()]), true),
+            ("not empty", group(AplConditionType::Not, vec![]), false),
+            ("not multiple", group(AplConditionType::Not, vec![never(), never()]), false),
+            ("not not", group(AplConditionType::Not, vec![group(AplConditionType::Not, vec![always()])]), true),
+            ("true zero", boolean(AplConditionType::True, vec![constant(0.0)]), false),
+            ("true one", boolean(AplConditionType::True, vec![constant(1.0)]), true),
+            // Only exactly 1 is true, 3 stacks are neither true nor false
+            ("true stacks", boolean(AplConditionType::True, vec![value(AplValueType::TargetAuraStacks, aura::FIRE_VULNERABILITY, 0.0)]), false),
+            ("true empty", boolean(AplConditionType::True, vec![]), false),
+            ("false zero", boolean(AplConditionType::False, vec![constant(0.0)]), true),
+            ("false one", boolean(AplConditionType::False, vec![constant(1.0)]), false),
+            ("false stacks", boolean(AplConditionType::False, vec![value(AplValueType::TargetAuraStacks, aura::FIRE_VULNERABILITY, 0.0)]), false),
+            ("false empty", boolean(AplConditionType::False, vec![]), false),
+            ("true aura missing", boolean(AplConditionType::True, vec![value(AplValueType::PlayerAuraExists, aura::COMBUSTION, 0.0)]), false),
+            ("false aura missing", boolean(AplConditionType::False, vec![value(AplValueType::PlayerAuraExists, aura::COMBUSTION, 0.0)]), true),
+        ];
+
+        for (name, condition, expected) in table {
+            assert_eq!(mage.apl_check_condition(&condition, 10.0, &targets, 1), expected, "{}", name);
+        }
+    }
+
+    fn test_multi_targets(mage: &Mage) -> HashMap<i32, Target> {
+        let mut targets = HashMap::new();
+        for i in 1..=3 {
+            let mut target = Target::new(i);
+            target.max_health = 10000.0;
+            targets.insert(i, target);
+        }
+
+        let stacks = [(1, 5), (2, 2), (3, 0)];
+        for (id, n) in stacks {
+            for _ in 0..n {
+                let mut aura = aura::fire_vulnerability();
+                aura.owner_id = mage.id;
+                targets.get_mut(&id).unwrap().auras.add(aura);
+            }
+        }
+
+        let mut ignite = aura::Aura::new(spell::IGNITE, String::from("Ignite"), 4.0);
+        ignite.owner_id = mage.id;
+        targets.get_mut(&1).unwrap().auras.add(ignite.clone());
+        ignite.owner_id = mage.id + 1;
+        targets.get_mut(&2).unwrap().auras.add(ignite);
+
+        targets.get_mut(&1).unwrap().add_dmg(mage.id, 500);
+        targets.get_mut(&2).unwrap().add_dmg(mage.id, 100);
+        targets.get_mut(&3).unwrap().add_dmg(mage.id, 300);
+
+        targets
+    }
+
+    fn target_action(target_type: apl::AplTargetType, vint: i32) -> apl::AplAction {
+        apl::AplAction {
+            key: apl::AplActionKey::Fireball,
+            target_id: 1,
+            target: apl::AplTarget { target_type, vint },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn apl_target_selection() {
+        let mut mage = test_mage(test_config());
+        let targets = test_multi_targets(&mage);
+        mage.target_id = 2;
+        mage.target_cycle = 4;
+
+        let table = vec![
+            (apl::AplTargetType::None, 0, 1),
+            (apl::AplTargetType::Current, 0, 2),
+            (apl::AplTargetType::LowestAuraStacks, aura::FIRE_VULNERABILITY, 3),
+            (apl::AplTargetType::MissingAura, spell::IGNITE, 2),
+            (apl::AplTargetType::HighestHealth, 0, 2),
+            (apl::AplTargetType::Cycle, 0, 2),
+            (apl::AplTargetType::Newest, 0, 3),
+        ];
+
+        for (target_type, vint, expected) in table {
+            let action = target_action(target_type.clone(), vint);
+            assert_eq!(mage.apl_target(&action, &targets), expected, "{:?}", target_type);
+            assert_eq!(mage.apl_action(&action, 0.0, &targets).target_id, expected, "{:?}", target_type);
+        }
+    }
+
+    #[test]
+    fn apl_target_conditions_use_selected_target() {
+        let mut config = test_config();
+        config.players[0].apl.items.push(apl::AplItem {
+            condition: boolean(AplConditionType::True, vec![value(AplValueType::TargetAuraExists, aura::FIRE_VULNERABILITY, 0.0)]),
+            action: target_action(apl::AplTargetType::Newest, 0),
+        });
+        config.players[0].apl.items.push(apl::AplItem {
+            condition: boolean(AplConditionType::False, vec![value(AplValueType::TargetAuraExists, aura::FIRE_VULNERABILITY, 0.0)]),
+            action: target_action(apl::AplTargetType::LowestAuraStacks, aura::FIRE_VULNERABILITY),
+        });
+
+        let mut mage = test_mage(config);
+        let targets = test_multi_targets(&mage);
+
+        // Newest target has no fire vulnerability, so the first item is skipped
+        let event = mage.apl_next_event(0.0, &targets);
+        assert_eq!(event.target_id, 3);
+        assert_eq!(mage.target_id, 3);
+    }
+
+    #[test]
+    fn apl_target_cycle() {
+        let mut config = test_config();
+        config.players[0].apl.items.push(apl::AplItem {
+            condition: always(),
+            action: target_action(apl::AplTargetType::Cycle, 0),
+        });
+
+        let mut mage = test_mage(config);
+        let targets = test_multi_targets(&mage);
+
+        for expected in [1, 2, 3, 1] {
+            assert_eq!(mage.apl_next_event(0.0, &targets).target_id, expected);
+        }
+    }
+
+    fn action_item(key: apl::AplActionKey) -> apl::AplItem {
+        apl::AplItem {
+            condition: always(),
+            action: apl::AplAction { key, target_id: 1, ..Default::default() },
+        }
+    }
+
+    fn list_item(key: apl::AplActionKey, list: &str) -> apl::AplItem {
+        let mut item = action_item(key);
+        item.action.list = String::from(list);
+        item
+    }
+
+    fn list(name: &str, items: Vec<apl::AplItem>) -> apl::AplList {
+        apl::AplList { name: String::from(name), items }
+    }
+
+    fn next_spell_id(config: Config) -> Option<i32> {
+        let mut mage = test_mage(config);
+        let targets = test_targets();
+        mage.apl_next_event(0.0, &targets).spell.map(|spell| spell.id)
+    }
+
+    #[test]
+    fn apl_lists() {
+        // Call list falls through when nothing in it is available
+        let mut config = test_config();
+        config.players[0].apl.lists.push(list("cooldowns", vec![action_item(apl::AplActionKey::PresenceOfMind)]));
+        config.players[0].apl.items = vec![list_item(apl::AplActionKey::CallList, "cooldowns"), action_item(apl::AplActionKey::Fireball)];
+        assert_eq!(next_spell_id(config), Some(spell::FIREBALL));
+
+        // Call list uses the first available action in it
+        let mut config = test_config();
+        config.players[0].apl.lists.push(list("filler", vec![action_item(apl::AplActionKey::Frostbolt)]));
+        config.players[0].apl.items = vec![list_item(apl::AplActionKey::CallList, "filler"), action_item(apl::AplActionKey::Fireball)];
+        assert_eq!(next_spell_id(config), Some(spell::FROSTBOLT));
+
+        // Run list stops even if nothing in it is available
+        let mut config = test_config();
+        config.players[0].apl.lists.push(list("cooldowns", vec![action_item(apl::AplActionKey::PresenceOfMind)]));
+        config.players[0].apl.items = vec![list_item(apl::AplActionKey::RunList, "cooldowns"), action_item(apl::AplActionKey::Fireball)];
+        let mut mage = test_mage(config);
+        let event = mage.apl_next_event(0.0, &test_targets());
+        assert!(event.spell.is_none());
+        assert_eq!(event.text, "APL: No available action");
+
+        // Shared lists can be overridden by player lists
+        let mut config = test_config();
+        config.apl_lists.push(list("filler", vec![action_item(apl::AplActionKey::Frostbolt)]));
+        config.players[0].apl.items = vec![list_item(apl::AplActionKey::RunList, "filler")];
+        assert_eq!(next_spell_id(config.clone()), Some(spell::FROSTBOLT));
+        config.players[0].apl.lists.push(list("filler", vec![action_item(apl::AplActionKey::Scorch)]));
+        assert_eq!(next_spell_id(config), Some(spell::SCORCH));
+
+        // Unknown and recursive lists fall through
+        let mut config = test_config();
+        config.players[0].apl.lists.push(list("loop", vec![list_item(apl::AplActionKey::CallList, "loop")]));
+        config.players[0].apl.items = vec![
+            list_item(apl::AplActionKey::CallList, "missing"),
+            list_item(apl::AplActionKey::CallList, "loop"),
+            action_item(apl::AplActionKey::Fireball),
+        ];
+        assert_eq!(next_spell_id(config), Some(spell::FIREBALL));
+    }
+
+    #[test]
+    fn cold_snap_resets_frost_cooldowns() {
+        let mut config = test_config();
+        config.players[0].talents[TALENT_COLD_SNAP] = 1;
+        config.players[0].talents[TALENT_ICE_BLOCK] = 1;
+        let mut mage = test_mage(config);
+
+        for spell in [spell::cone_of_cold(), spell::frost_nova(), spell::ice_block(), spell::fire_blast(), spell::cold_snap()] {
+            let cooldown = mage.spell_cooldown_event(spell).cooldown.unwrap();
+            mage.cooldowns.add(cooldown);
+        }
+
+        let mut event = mage.spell_event(mage.this_spell(spell::cold_snap()), 0);
+        event.event_type = EventType::CastSuccess;
+        let events = mage.on_event(&event);
+
+        let mut ids: Vec<i32> = events.iter()
+            .filter(|ev| ev.event_type == EventType::CooldownExpire)
+            .map(|ev| ev.cooldown.as_ref().unwrap().id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec![spell::CONE_OF_COLD, spell::FROST_NOVA, spell::ICE_BLOCK]);
+    }
+
+    fn talent_mage(talent: usize, points: u8) -> Mage {
+        let mut config = test_config();
+        config.players[0].talents[talent] = points;
+        test_mage(config)
+    }
+
+    #[test]
+    fn arcane_mind() {
+        assert_eq!(talent_mage(TALENT_ARCANE_MIND, 0).max_mana(), 1213.0 + 300.0 * 15.0 - 280.0);
+        assert_eq!(talent_mage(TALENT_ARCANE_MIND, 5).max_mana(), (1213.0 + 300.0 * 15.0 - 280.0) * 1.1);
+    }
+
+    #[test]
+    fn arcane_meditation() {
+        // 100 spirit gives 18.75 mana per second, none of it while casting without talents
+        let mut mage = talent_mage(TALENT_ARCANE_MEDITATION, 0);
+        mage.stats.spi = 100.0;
+        mage.t_mana_spent = 10.0;
+        assert_eq!(mage.mana_per_second(12.0), 0.0);
+        assert_eq!(mage.mana_per_second(16.0), 18.75);
+
+        let mut mage = talent_mage(TALENT_ARCANE_MEDITATION, 3);
+        mage.stats.spi = 100.0;
+        mage.t_mana_spent = 10.0;
+        assert!((mage.mana_per_second(12.0) - 18.75 * 0.15).abs() < 0.000001);
+        assert_eq!(mage.mana_per_second(16.0), 18.75);
+    }
+
+    #[test]
+    fn imp_arcane_explosion() {
+        let base = talent_mage(TALENT_IMP_ARCANE_EXPLOSION, 0);
+        let mage = talent_mage(TALENT_IMP_ARCANE_EXPLOSION, 3);
+        assert_eq!(mage.spell_crit_chance(&spell::arcane_explosion()) - base.spell_crit_chance(&spell::arcane_explosion()), 6.0);
+        assert_eq!(mage.spell_crit_chance(&spell::arcane_missiles()), base.spell_crit_chance(&spell::arcane_missiles()));
+    }
+
+    fn impact_event(spell: spell::Spell, result: spell::SpellResult, dmg: f64) -> Event {
+        let mut instance = spell::SpellInstance::new(spell);
+        instance.result = result;
+        instance.dmg = dmg;
+
+        Event {
+            event_type: EventType::SpellImpact,
+            unit_id: 1,
+            target_id: 1,
+            spell_instance: Some(instance),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn ignite() {
+        // Any rank ignites for 20% of the crit
+        for (points, dmg) in [(1, 200.0), (3, 200.0), (5, 200.0)] {
+            let mut mage = talent_mage(TALENT_IGNITE, points);
+            let events = mage.on_event(&impact_event(spell::fireball(), spell::SpellResult::Crit, 1000.0));
+            let ignite = events.iter().find_map(|ev| ev.spell.as_ref().filter(|spell| spell.id == spell::IGNITE)).unwrap();
+            assert_eq!(ignite.min_dmg, dmg);
+        }
+
+        let mut mage = talent_mage(TALENT_IGNITE, 5);
+        let events = mage.on_event(&impact_event(spell::fireball(), spell::SpellResult::Hit, 1000.0));
+        assert!(events.iter().all(|ev| ev.spell.as_ref().is_none_or(|spell| spell.id != spell::IGNITE)));
+        let events = mage.on_event(&impact_event(spell::frostbolt(), spell::SpellResult::Crit, 1000.0));
+        assert!(events.iter().all(|ev| ev.spell.as_ref().is_none_or(|spell| spell.id != spell::IGNITE)));
+    }
+
+    #[test]
+    fn critical_mass() {
+        let base = talent_mage(TALENT_CRITICAL_MASS, 0);
+        let mage = talent_mage(TALENT_CRITICAL_MASS, 3);
+        assert_eq!(mage.spell_crit_chance(&spell::fireball()) - base.spell_crit_chance(&spell::fireball()), 6.0);
+        assert_eq!(mage.spell_crit_chance(&spell::frostbolt()), base.spell_crit_chance(&spell::frostbolt()));
+    }
+
+    #[test]
+    fn fire_power() {
+        let mage = talent_mage(TALENT_FIRE_POWER, 5);
+        assert!((mage.buff_spell_dmg_multiplier(&spell::fireball()) - 1.1).abs() < 0.000001);
+        assert_eq!(mage.buff_spell_dmg_multiplier(&spell::frostbolt()), 1.0);
+        assert_eq!(mage.buff_spell_dmg_multiplier(&spell::ignite(100.0)), 1.0);
+    }
+
+    #[test]
+    fn incinerate() {
+        let base = talent_mage(TALENT_INCINERATE, 0);
+        let mage = talent_mage(TALENT_INCINERATE, 2);
+        assert_eq!(mage.spell_crit_chance(&spell::scorch()) - base.spell_crit_chance(&spell::scorch()), 4.0);
+        assert_eq!(mage.spell_crit_chance(&spell::fire_blast()) - base.spell_crit_chance(&spell::fire_blast()), 4.0);
+        assert_eq!(mage.spell_crit_chance(&spell::fireball()), base.spell_crit_chance(&spell::fireball()));
+    }
+
+    #[test]
+    fn imp_scorch() {
+        let count_procs = |points: u8| {
+            let mut mage = talent_mage(TALENT_IMP_SCORCH, points);
+            mage.new_rng(1);
+            (0..3000).filter(|_| {
+                let events = mage.on_event(&impact_event(spell::scorch(), spell::SpellResult::Hit, 250.0));
+                events.iter().any(|ev| ev.spell.as_ref().is_some_and(|spell| spell.id == spell::FIRE_VULNERABILITY))
+            }).count()
+        };
+
+        // 50% with 1 point, 2 points are enough to always apply it
+        assert_eq!(count_procs(0), 0);
+        assert!((1400..1600).contains(&count_procs(1)));
+        assert_eq!(count_procs(2), 3000);
+        assert_eq!(count_procs(3), 3000);
+    }
+
+    #[test]
+    fn master_of_elements() {
+        let mut mage = talent_mage(TALENT_MASTER_OF_ELEMENTS, 3);
+        let events = mage.on_event(&impact_event(spell::frostbolt(), spell::SpellResult::Crit, 1000.0));
+        let mana: f64 = events.iter().filter(|ev| ev.event_type == EventType::ManaGain).map(|ev| ev.mana).sum();
+        assert!((mana - 290.0 * 0.3).abs() < 0.000001);
+
+        let events = mage.on_event(&impact_event(spell::frostbolt(), spell::SpellResult::Hit, 1000.0));
+        assert!(events.iter().all(|ev| ev.event_type != EventType::ManaGain));
+        let events = mage.on_event(&impact_event(spell::arcane_missiles(), spell::SpellResult::Crit, 1000.0));
+        assert!(events.iter().all(|ev| ev.event_type != EventType::ManaGain));
+    }
+
+    #[test]
+    fn flame_throwing() {
+        // Standing at 40 yards puts fireball out of range without talents
+        let mage = talent_mage(TALENT_FLAME_THROWING, 0);
+        assert_eq!(mage.this_spell(spell::fireball()).travel_time(40.0), 35.0 / 24.0);
+        assert_eq!(mage.this_spell(spell::fireball()).travel_time(30.0), 30.0 / 24.0);
+
+        let mage = talent_mage(TALENT_FLAME_THROWING, 2);
+        assert_eq!(mage.this_spell(spell::fireball()).travel_time(40.0), 40.0 / 24.0);
+        assert_eq!(mage.this_spell(spell::frostbolt()).travel_time(40.0), 30.0 / 28.0);
+    }
+
+    #[test]
+    fn burning_soul() {
+        let mage = talent_mage(TALENT_BURNING_SOUL, 2);
+        assert!((mage.spell_threat_multiplier(&spell::fireball()) - 0.7).abs() < 0.000001);
+        assert!((mage.spell_threat_multiplier(&spell::ignite(100.0)) - 0.7).abs() < 0.000001);
+        assert_eq!(mage.spell_threat_multiplier(&spell::frostbolt()), 1.0);
+    }
+
+    #[test]
+    fn imp_flamestrike() {
+        let base = talent_mage(TALENT_IMP_FLAMESTRIKE, 0);
+        let mage = talent_mage(TALENT_IMP_FLAMESTRIKE, 3);
+        assert_eq!(mage.spell_crit_chance(&spell::flamestrike()) - base.spell_crit_chance(&spell::flamestrike()), 15.0);
+        assert_eq!(mage.spell_crit_chance(&spell::fireball()), base.spell_crit_chance(&spell::fireball()));
+    }
+
+    #[test]
+    fn blast_wave() {
+        let action = apl::AplAction { key: apl::AplActionKey::BlastWave, target_id: 1, ..Default::default() };
+        assert!(talent_mage(TALENT_BLAST_WAVE, 0).apl_action(&action, 0.0, &test_targets()).spell.is_none());
+        assert!(talent_mage(TALENT_BLAST_WAVE, 1).apl_action(&action, 0.0, &test_targets()).spell.is_some());
+    }
+
+    #[test]
+    fn frost_channeling() {
+        let mage = talent_mage(TALENT_FROST_CHANNELING, 3);
+        assert!((mage.spell_mana_cost(&spell::frostbolt()) - 290.0 * 0.85).abs() < 0.000001);
+        assert_eq!(mage.spell_mana_cost(&spell::fireball()), 410.0);
+        assert!((mage.spell_threat_multiplier(&spell::frostbolt()) - 0.7).abs() < 0.000001);
+    }
+
+    #[test]
+    fn imp_cone_of_cold() {
+        for (points, multi) in [(0, 1.0), (1, 1.15), (2, 1.25), (3, 1.35)] {
+            let mage = talent_mage(TALENT_IMP_CONE_OF_COLD, points);
+            assert!((mage.buff_spell_dmg_multiplier(&spell::cone_of_cold()) - multi).abs() < 0.000001);
+            assert_eq!(mage.buff_spell_dmg_multiplier(&spell::frost_nova()), 1.0);
+        }
+    }
+
+    #[test]
+    fn arctic_reach() {
+        let mage = talent_mage(TALENT_ARCTIC_REACH, 2);
+        assert_eq!(mage.this_spell(spell::frostbolt()).travel_time(40.0), 36.0 / 28.0);
+        assert_eq!(mage.this_spell(spell::fireball()).travel_time(40.0), 35.0 / 24.0);
+    }
+
+    #[test]
+    fn frozen_targets() {
+        let is_frozen = |ev: &Event| ev.event_type == EventType::AuraGain && ev.target_id == 1 && ev.aura.as_ref().is_some_and(|aura| aura.id == aura::FROST_NOVA || aura.id == aura::FROSTBITE);
+
+        // Frost nova always freezes, but only targets that can be frozen
+        let mut mage = test_mage(test_config());
+        let events = mage.on_event(&impact_event(spell::frost_nova(), spell::SpellResult::Hit, 75.0));
+        assert!(!events.iter().any(is_frozen));
+
+        let mut config = test_config();
+        config.target_freezable = true;
+        let mut mage = test_mage(config);
+        let events = mage.on_event(&impact_event(spell::frost_nova(), spell::SpellResult::Hit, 75.0));
+        assert!(events.iter().any(is_frozen));
+        let events = mage.on_event(&impact_event(spell::frost_nova(), spell::SpellResult::Miss, 0.0));
+        assert!(!events.iter().any(is_frozen));
+
+        // Frostbite procs 5% per point from frostbolt
+        let mut config = test_config();
+        config.target_freezable = true;
+        config.players[0].talents[TALENT_FROSTBITE] = 3;
+        let mut mage = test_mage(config);
+        mage.new_rng(1);
+        let procs = (0..3000).filter(|_| {
+            mage.on_event(&impact_event(spell::frostbolt(), spell::SpellResult::Hit, 500.0)).iter().any(is_frozen)
+        }).count();
+        assert!((350..550).contains(&procs));
+        let events = mage.on_event(&impact_event(spell::fireball(), spell::SpellResult::Hit, 500.0));
+        assert!(!events.iter().any(is_frozen));
+    }
+
+    #[test]
+    fn apl_spell_ranks() {
+        let mage = test_mage(test_config());
+        let targets = test_targets();
+        let ranked = |key: apl::AplActionKey, rank: i32| {
+            let action = apl::AplAction { key, target_id: 1, rank, ..Default::default() };
+            mage.apl_action(&action, 0.0, &targets).spell.unwrap()
+        };
+
+        // Rank 0 and ranks out of range are the highest rank
+        assert_eq!(ranked(apl::AplActionKey::Fireball, 0).rank, 12);
+        assert_eq!(ranked(apl::AplActionKey::Fireball, 20).rank, 12);
+        assert_eq!(ranked(apl::AplActionKey::Scorch, 0).rank, 7);
+
+        let frostbolt = ranked(apl::AplActionKey::Frostbolt, 9);
+        assert_eq!((frostbolt.rank, frostbolt.min_dmg, frostbolt.max_dmg, frostbolt.mana_cost), (9, 353.0, 383.0, 225.0));
+        let scorch = ranked(apl::AplActionKey::Scorch, 4);
+        assert_eq!((scorch.rank, scorch.min_dmg, scorch.max_dmg, scorch.mana_cost), (4, 139.0, 165.0, 100.0));
+
+        // Spells below level 20 have a reduced coefficient
+        assert!((ranked(apl::AplActionKey::Fireball, 1).coeff - 1.5 / 3.5 * 0.2875).abs() < 0.000001);
+        assert!((ranked(apl::AplActionKey::Frostbolt, 1).coeff - 1.5 / 3.5 * 0.95 * 0.4).abs() < 0.000001);
+        assert!((ranked(apl::AplActionKey::ArcaneMissiles, 1).coeff - 0.24 * 0.55).abs() < 0.000001);
+        assert_eq!(ranked(apl::AplActionKey::Fireball, 11).coeff, 1.0);
+        assert_eq!(ranked(apl::AplActionKey::Scorch, 1).coeff, 0.429);
+    }
+
+    #[test]
+    fn race_stats() {
+        // Stats are used as is unless they are gear only
+        let mut config = test_config();
+        config.players[0].race = common::Race::Gnome;
+        let mage = test_mage(config.clone());
+        assert_eq!(mage.player_stats().int, 300.0);
+
+        config.players[0].gear_stats = true;
+        let stats = test_mage(config.clone()).player_stats();
+        assert_eq!(stats.int, ((300.0 + 132.0) * 1.05_f64).round());
+        assert_eq!(stats.spi, 120.0);
+        assert!((stats.crit - 0.2 - stats.int / 59.5).abs() < 0.000001);
+
+        config.players[0].race = common::Race::Human;
+        let stats = test_mage(config.clone()).player_stats();
+        assert_eq!(stats.int, 425.0);
+        assert_eq!(stats.spi, (126.0 * 1.05_f64).round());
+
+        config.players[0].race = common::Race::Undead;
+        let stats = test_mage(config).player_stats();
+        assert_eq!((stats.int, stats.spi), (423.0, 125.0));
+
+        assert!(common::Race::Tauren.is_horde());
+        assert!(!common::Race::Dwarf.is_horde());
+    }
+
+    #[test]
+    fn buff_stats() {
+        let mut config = test_config();
+        config.players[0].race = common::Race::Human;
+        config.players[0].gear_stats = true;
+        let base = test_mage(config.clone()).player_stats();
+
+        config.players[0].buffs = vec![
+            buff::Buff::ArcaneIntellect,
+            buff::Buff::BlessingOfKings,
+            buff::Buff::SpiritOfZandalar,
+            buff::Buff::GreaterArcaneElixir,
+            buff::Buff::ArcaneElixir,
+            buff::Buff::BrilliantWizardOil,
+            buff::Buff::ManaSpring,
+        ];
+        let stats = test_mage(config.clone()).player_stats();
+        assert_eq!(stats.int, ((300.0 + 125.0 + 31.0) * 1.1 * 1.15_f64).round());
+        assert_eq!(stats.spi, (126.0 * 1.05 * 1.1 * 1.15_f64).round());
+        assert_eq!(stats.sp - base.sp, 71.0);
+        assert_eq!(stats.mp5, base.mp5);
+
+        // Buffs are not added to final stats
+        config.players[0].gear_stats = false;
+        assert_eq!(test_mage(config.clone()).player_stats().int, 300.0);
+
+        // Damage buffs don't stack with their old flags
+        config.players[0].buffs = vec![buff::Buff::DarkmoonFaireDmg, buff::Buff::TracesOfSilithyst];
+        config.players[0].dmf_dmg = true;
+        let mage = test_mage(config);
+        assert!((mage.buff_spell_dmg_multiplier(&spell::frostbolt()) - 1.1 * 1.05).abs() < 0.000001);
+    }
+
+    #[test]
+    fn berserking() {
+        let mut config = test_config();
+        config.players[0].race = common::Race::Troll;
+
+        for (missing_health, haste) in [(0.0, 1.1), (30.0, 1.2), (60.0, 1.3), (90.0, 1.3)] {
+            config.players[0].missing_health = missing_health;
+            let mut mage = test_mage(config.clone());
+            assert_eq!(mage.spell_haste(), 1.0);
+            mage.auras.add(aura::berserking());
+            assert!((mage.spell_haste() - 1.0 / haste).abs() < 0.000001);
+        }
+
+        let mut mage = test_mage(config);
+        let mut event = mage.spell_event(mage.this_spell(spell::berserking()), 0);
+        event.event_type = EventType::CastSuccess;
+        let events = mage.on_event(&event);
+        assert!(events.iter().any(|ev| ev.aura.as_ref().is_some_and(|aura| aura.id == aura::BERSERKING)));
+    }
+}
@@ -0,0 +1,180 @@
+use crate::common;
+use crate::config::Config;
+use crate::config::PlayerConfig;
+use crate::sim;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+// A named variant of the base config
+// Patches are json merge patches, player_patch is applied to the players in player_ids or all players when empty
+#[derive(Default, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Profile {
+    pub name: String,
+    pub player_ids: Vec<i32>,
+    pub player_patch: Value,
+    pub config_patch: Value,
+}
+
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct ProfileResult {
+    pub name: String,
+    pub dps: f64,
+    pub min_dps: f64,
+    pub max_dps: f64,
+    // Difference to the base config with the half width of its 95% confidence interval
+    pub delta: f64,
+    pub delta_percent: f64,
+    pub delta_ci: f64,
+    pub is_significant: bool,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct ProfileSetResult {
+    pub iterations: i32,
+    pub rng_seed: u64,
+    // Best first, the base config is included with the name "Base"
+    pub profiles: Vec<ProfileResult>,
+}
+
+// Every profile runs the same seeds as the base config
+// so the differences come from the changes and not from the rolls
+pub fn run(mut config: Config, profiles: &[Profile], iterations: i32) -> Result<ProfileSetResult, String> {
+    sim::fix_rng_seed(&mut config);
+    config.keep_iterations = true;
+
+    let mut configs = vec![(String::from("Base"), config.clone())];
+    for profile in profiles.iter() {
+        configs.push((profile.name.clone(), apply(&config, profile)?));
+    }
+
+    let mut runs = vec![];
+    for (name, c) in configs {
+        let result = sim::run_multiple(c, iterations)?;
+        let dps: Vec<f64> = result.iteration_results.iter().map(|r| r.dps).collect();
+        runs.push((name, result, dps));
+    }
+
+    let base = runs[0].2.clone();
+    let mut result = ProfileSetResult { iterations, rng_seed: config.rng_seed, profiles: vec![] };
+
+    for (name, r, dps) in runs {
+        let diffs: Vec<f64> = dps.iter().zip(base.iter()).map(|(a, b)| a - b).collect();
+        let (delta, delta_ci) = common::mean_ci(&diffs);
+        let base_dps = base.iter().sum::<f64>() / base.len().max(1) as f64;
+
+        result.profiles.push(ProfileResult {
+            name,
+            dps: r.dps,
+            min_dps: r.min_dps,
+            max_dps: r.max_dps,
+            delta,
+            delta_percent: if base_dps > 0.0 { delta / base_dps * 100.0 } else { 0.0 },
+            delta_ci,
+            is_significant: delta.abs() > delta_ci,
+        });
+    }
+
+    result.profiles.sort_by(|a, b| b.dps.total_cmp(&a.dps));
+
+    Ok(result)
+}
+
+pub fn apply(config: &Config, profile: &Profile) -> Result<Config, String> {
+    let mut config = config.clone();
+
+    if !profile.config_patch.is_null() {
+        config = patched(&config, &profile.config_patch).map_err(|e| format!("{}: {}", profile.name, e))?;
+    }
+
+    if !profile.player_patch.is_null() {
+        for (i, player) in config.players.iter_mut().enumerate() {
+            if profile.player_ids.is_empty() || profile.player_ids.contains(&(i as i32 + 1)) {
+                *player = patched::<PlayerConfig>(player, &profile.player_patch).map_err(|e| format!("{} player {}: {}", profile.name, i + 1, e))?;
+            }
+        }
+    }
+
+    Ok(config)
+}
+
+fn patched<T: Serialize + for<'de> Deserialize<'de>>(value: &T, patch: &Value) -> Result<T, String> {
+    let mut json = serde_json::to_value(value).map_err(|e| e.to_string())?;
+    merge_patch(&mut json, patch);
+
+    serde_json::from_value(json).map_err(|e| e.to_string())
+}
+
+// Json merge patch (RFC 7386), null removes a field and objects are merged
+fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    let target = target.as_object_mut().unwrap();
+
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::tests::fireball_config;
+    use serde_json::json;
+
+    #[test]
+    fn merge() {
+        let mut value = json!({"a": 1, "b": {"c": 2, "d": 3}});
+        merge_patch(&mut value, &json!({"b": {"c": 5, "d": null}, "e": [1]}));
+        assert_eq!(value, json!({"a": 1, "b": {"c": 5}, "e": [1]}));
+    }
+
+    #[test]
+    fn patches() {
+        let config = Config { players: vec![PlayerConfig::default(), PlayerConfig::default()], ..Default::default() };
+        let profile = Profile {
+            name: String::from("More sp"),
+            player_ids: vec![2],
+            player_patch: json!({"stats": {"sp": 100.0}}),
+            config_patch: json!({"duration": 60.0}),
+        };
+
+        let patched = apply(&config, &profile).unwrap();
+        assert_eq!(patched.duration, 60.0);
+        assert_eq!(patched.players[0].stats.sp, 0.0);
+        assert_eq!(patched.players[1].stats.sp, 100.0);
+
+        let profile = Profile { name: String::from("Bad"), player_patch: json!({"level": "sixty"}), ..Default::default() };
+        assert!(apply(&config, &profile).err().unwrap().starts_with("Bad player 1:"));
+    }
+
+    #[test]
+    fn ranking() {
+        let config = fireball_config(60.0);
+
+        let profiles = vec![
+            Profile { name: String::from("Same"), player_patch: json!({"name": "Renamed"}), ..Default::default() },
+            Profile { name: String::from("More sp"), player_patch: json!({"stats": {"sp": 300.0}}), ..Default::default() },
+        ];
+        let result = run(config, &profiles, 30).unwrap();
+        let names: Vec<&str> = result.profiles.iter().map(|p| p.name.as_str()).collect();
+
+        assert_eq!(names[0], "More sp");
+        assert!(result.profiles[0].is_significant && result.profiles[0].delta > 0.0);
+        // Identical configs roll the same numbers
+        for p in result.profiles.iter().filter(|p| p.name != "More sp") {
+            assert_eq!(p.delta, 0.0);
+            assert!(!p.is_significant);
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use crate::common::School;
+use crate::log::LogEntry;
+use crate::log::LogType;
+use crate::sim::SimulationResult;
+use crate::sim::SimulationsResult;
+use crate::spell::SpellResult;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+
+/*
+ * Standalone html report
+ * Everything is inlined so the file can be shared without the web app
+ */
+
+const STYLE: &str = "
+body { font-family: sans-serif; background: #1b1b1f; color: #ddd; margin: 2em; }
+h1, h2 { color: #fff; font-weight: normal; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { padding: 4px 12px; text-align: right; border-bottom: 1px solid #333; }
+th:first-child, td:first-child { text-align: left; }
+svg { background: #242429; margin-bottom: 2em; }
+svg text { fill: #aaa; font-size: 11px; }
+";
+
+const HISTOGRAM_WIDTH: f64 = 800.0;
+const HISTOGRAM_HEIGHT: f64 = 200.0;
+const TIMELINE_WIDTH: f64 = 1000.0;
+const TIMELINE_ROW: f64 = 24.0;
+const TIMELINE_LABEL: f64 = 100.0;
+
+// The log is optional, spells, auras and casts are only shown with it
+pub fn html(result: &SimulationsResult, run: Option<&SimulationResult>) -> String {
+    let mut body = String::new();
+
+    body.push_str("<h1>MageSim report</h1>\n");
+    body.push_str(&summary(result));
+    body.push_str("<h2>DPS distribution</h2>\n");
+    body.push_str(&histogram(&result.histogram));
+    body.push_str("<h2>Players</h2>\n");
+    body.push_str(&players(result));
+
+    if let Some(run) = run {
+        body.push_str("<h2>Spells</h2>\n");
+        body.push_str(&spells(&run.log));
+        body.push_str("<h2>Aura uptime</h2>\n");
+        body.push_str(&auras(&run.log, run.t));
+        body.push_str("<h2>Casts</h2>\n");
+        body.push_str(&timeline(&run.log, run.t));
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>MageSim report</title>\n<style>{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        STYLE, body
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn table(header: &[&str], rows: Vec<Vec<String>>) -> String {
+    let mut out = String::from("<table>\n<tr>");
+    for h in header {
+        out.push_str(&format!("<th>{}</th>", h));
+    }
+    out.push_str("</tr>\n");
+
+    for row in rows {
+        out.push_str("<tr>");
+        for cell in row {
+            out.push_str(&format!("<td>{}</td>", cell));
+        }
+        out.push_str("</tr>\n");
+    }
+
+    out.push_str("</table>\n");
+    out
+}
+
+fn school_color(school: School) -> &'static str {
+    match school {
+        School::Arcane => "#c97fe8",
+        School::Fire => "#f0772f",
+        School::Frost => "#69ccf0",
+        School::Nature => "#7cd35b",
+        School::Shadow => "#9482c9",
+        School::Holy => "#f5e07a",
+        _ => "#999999",
+    }
+}
+
+fn summary(result: &SimulationsResult) -> String {
+    table(&["Iterations", "DPS", "Min", "Max", "Ignite DPS"], vec![vec![
+        result.iterations.to_string(),
+        format!("{:.1}", result.dps),
+        format!("{:.1}", result.min_dps),
+        format!("{:.1}", result.max_dps),
+        format!("{:.1}", result.ignite_dps),
+    ]])
+}
+
+fn histogram(histogram: &HashMap<u32, u32>) -> String {
+    let mut bins: Vec<(u32, u32)> = histogram.iter().map(|(bin, n)| (*bin, *n)).collect();
+    bins.sort();
+
+    let max = bins.iter().map(|(_, n)| *n).max().unwrap_or(1) as f64;
+    let width = HISTOGRAM_WIDTH / bins.len().max(1) as f64;
+    let mut out = format!("<svg width=\"{}\" height=\"{}\">\n", HISTOGRAM_WIDTH, HISTOGRAM_HEIGHT + 20.0);
+
+    for (i, (bin, n)) in bins.iter().enumerate() {
+        let height = *n as f64 / max * HISTOGRAM_HEIGHT;
+        let x = i as f64 * width;
+        out.push_str(&format!(
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"#69ccf0\"><title>{} dps: {}</title></rect>\n",
+            x + 1.0, HISTOGRAM_HEIGHT - height, (width - 2.0).max(1.0), height, bin, n
+        ));
+        out.push_str(&format!("<text x=\"{:.1}\" y=\"{}\">{}</text>\n", x + 1.0, HISTOGRAM_HEIGHT + 15.0, bin));
+    }
+
+    out.push_str("</svg>\n");
+    out
+}
+
+fn players(result: &SimulationsResult) -> String {
+    let rows = result.players.iter().map(|p| vec![
+        escape(&p.name),
+        format!("{:?}", p.class),
+        format!("{:.1}", p.dps),
+        format!("{:.1}", p.ignite_dps),
+        format!("{:.1}", p.tps),
+        format!("{:.0}%", p.mana.oom_rate * 100.0),
+    ]).collect();
+
+    table(&["Name", "Class", "DPS", "Ignite DPS", "TPS", "OOM"], rows)
+}
+
+#[derive(Default)]
+struct SpellStats {
+    casts: u32,
+    hits: u32,
+    crits: u32,
+    misses: u32,
+    dmg: f64,
+}
+
+fn spells(log: &[LogEntry]) -> String {
+    let mut stats: HashMap<String, SpellStats> = HashMap::new();
+
+    for entry in log.iter() {
+        if entry.spell_name.is_empty() {
+            continue;
+        }
+        let s = stats.entry(entry.spell_name.clone()).or_default();
+
+        match entry.log_type {
+            LogType::CastSuccess => s.casts+= 1,
+            LogType::SpellImpact => {
+                match entry.spell_result {
+                    SpellResult::Crit => s.crits+= 1,
+                    SpellResult::Miss => s.misses+= 1,
+                    _ => s.hits+= 1,
+                }
+                s.dmg+= entry.value;
+            }
+            _ => {}
+        }
+    }
+
+    let total: f64 = stats.values().map(|s| s.dmg).sum();
+    let mut names: Vec<&String> = stats.keys().filter(|name| stats[*name].dmg > 0.0 || stats[*name].casts > 0).collect();
+    names.sort_by(|a, b| stats[*b].dmg.total_cmp(&stats[*a].dmg).then(a.cmp(b)));
+
+    let rows = names.into_iter().map(|name| {
+        let s = &stats[name];
+        let landed = s.hits + s.crits;
+        vec![
+            escape(name),
+            s.casts.to_string(),
+            format!("{:.0}", s.dmg),
+            format!("{:.1}%", if total > 0.0 { s.dmg / total * 100.0 } else { 0.0 }),
+            format!("{:.1}%", if landed > 0 { s.crits as f64 / landed as f64 * 100.0 } else { 0.0 }),
+            s.misses.to_string(),
+        ]
+    }).collect();
+
+    table(&["Spell", "Casts", "Damage", "Share", "Crit", "Misses"], rows)
+}
+
+// Unit, target and aura id
+type AuraKey = (i32, i32, i32);
+
+fn auras(log: &[LogEntry], t: f64) -> String {
+    // Time each aura was up, refreshes and new stacks don't restart it
+    let mut active: HashMap<AuraKey, f64> = HashMap::new();
+    let mut uptime: HashMap<AuraKey, f64> = HashMap::new();
+    let mut rows: Vec<(String, String, String, AuraKey)> = vec![];
+
+    for entry in log.iter() {
+        let key = (entry.unit_id, entry.target_id, entry.aura_id);
+
+        match entry.log_type {
+            LogType::AuraGain => {
+                active.entry(key).or_insert(entry.t);
+                if let Entry::Vacant(e) = uptime.entry(key) {
+                    e.insert(0.0);
+                    let on = if entry.target_id != 0 { &entry.target_name } else { &entry.unit_name };
+                    rows.push((entry.aura_name.clone(), entry.unit_name.clone(), on.clone(), key));
+                }
+            }
+            LogType::AuraExpire => {
+                if let Some(t_gain) = active.remove(&key) {
+                    *uptime.entry(key).or_insert(0.0)+= entry.t - t_gain;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Auras that are still up when the fight ends
+    for (key, t_gain) in active {
+        *uptime.entry(key).or_insert(0.0)+= t - t_gain;
+    }
+
+    rows.sort();
+    let rows = rows.into_iter().map(|(aura, unit, on, key)| vec![
+        escape(&aura),
+        escape(&unit),
+        escape(&on),
+        format!("{:.1}%", uptime[&key] / t * 100.0),
+    ]).collect();
+
+    table(&["Aura", "Unit", "On", "Uptime"], rows)
+}
+
+fn timeline(log: &[LogEntry], t: f64) -> String {
+    let mut units: Vec<(i32, String)> = vec![];
+    for entry in log.iter() {
+        if entry.log_type == LogType::CastStart && !units.iter().any(|(id, _)| *id == entry.unit_id) {
+            units.push((entry.unit_id, entry.unit_name.clone()));
+        }
+    }
+    units.sort();
+
+    let scale = (TIMELINE_WIDTH - TIMELINE_LABEL) / t.max(1.0);
+    let height = units.len() as f64 * TIMELINE_ROW;
+    let mut out = format!("<svg width=\"{}\" height=\"{}\">\n", TIMELINE_WIDTH, height + 20.0);
+
+    for (row, (unit_id, name)) in units.iter().enumerate() {
+        let y = row as f64 * TIMELINE_ROW;
+        out.push_str(&format!("<text x=\"4\" y=\"{:.1}\">{}</text>\n", y + 16.0, escape(name)));
+
+        // Bars as long as the cast time, instants get a thin mark
+        for entry in log.iter().filter(|e| e.log_type == LogType::CastStart && e.unit_id == *unit_id && e.spell_id > 0) {
+            let width = (entry.value * scale).max(2.0);
+            out.push_str(&format!(
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{}\" fill=\"{}\"><title>{:.2} {}</title></rect>\n",
+                TIMELINE_LABEL + entry.t * scale, y + 4.0, (width - 1.0).max(1.0), TIMELINE_ROW - 8.0,
+                school_color(entry.school), entry.t, escape(&entry.spell_name)
+            ));
+        }
+    }
+
+    let mut s = 0.0;
+    while s <= t {
+        out.push_str(&format!("<text x=\"{:.1}\" y=\"{}\">{}s</text>\n", TIMELINE_LABEL + s * scale, height + 15.0, s));
+        s+= 30.0;
+    }
+
+    out.push_str("</svg>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::PlayerResult;
+
+    fn entry(log_type: LogType, t: f64) -> LogEntry {
+        LogEntry {
+            log_type,
+            t,
+            unit_id: 1,
+            unit_name: String::from("<Mage>"),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn report() {
+        let result = SimulationsResult {
+            iterations: 10,
+            dps: 1000.0,
+            players: vec![PlayerResult { name: String::from("<Mage>"), dps: 1000.0, ..Default::default() }],
+            histogram: HashMap::from([(950, 4), (1000, 6)]),
+            ..Default::default()
+        };
+
+        let out = html(&result, None);
+        assert!(out.starts_with("<!DOCTYPE html>"));
+        assert!(out.contains("&lt;Mage&gt;") && !out.contains("<Mage>"));
+        assert!(out.contains("1000 dps: 6"));
+        assert!(!out.contains("Aura uptime"));
+    }
+
+    #[test]
+    fn uptime() {
+        let mut gain = entry(LogType::AuraGain, 10.0);
+        gain.aura_id = 1;
+        gain.aura_name = String::from("Arcane Power");
+        let mut refresh = gain.clone();
+        refresh.t = 20.0;
+        let mut expire = gain.clone();
+        expire.log_type = LogType::AuraExpire;
+        expire.t = 30.0;
+        let mut again = gain.clone();
+        again.t = 90.0;
+
+        // Up from 10 to 30 and from 90 to the end
+        let out = auras(&[gain, refresh, expire, again], 100.0);
+        assert!(out.contains("<td>Arcane Power</td><td>&lt;Mage&gt;</td><td>&lt;Mage&gt;</td><td>30.0%</td>"));
+    }
+}
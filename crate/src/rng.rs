@@ -0,0 +1,87 @@
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashMap;
+
+// Random mechanics that get their own stream of numbers
+// An extra roll in one of them doesn't change the rolls of the others
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Stream {
+    Duration,
+    Hit,
+    Crit,
+    Damage,
+    Resist,
+    Proc,
+    Consumable,
+    Debuff,
+}
+
+// Who rolls the numbers
+// The sim and the units share the seed, but never the streams
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Owner {
+    Sim,
+    Unit,
+}
+
+// Streams for every mechanic and unit, all derived from one seed
+pub struct Rngs {
+    seed: u64,
+    owner: Owner,
+    streams: HashMap<(Stream, i32), ChaCha8Rng>,
+}
+
+impl Rngs {
+    // A random seed is used when it's 0
+    pub fn new(seed: u64, owner: Owner) -> Self {
+        let seed = if seed == 0 { ChaCha8Rng::from_entropy().gen() } else { seed };
+
+        Self { seed, owner, streams: HashMap::new() }
+    }
+
+    pub fn get(&mut self, stream: Stream, unit_id: i32) -> &mut ChaCha8Rng {
+        let seed = self.seed;
+        let owner = self.owner;
+
+        self.streams.entry((stream, unit_id)).or_insert_with(|| {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            rng.set_stream(((owner as u64) << 48) | ((stream as u64) << 32) | unit_id as u32 as u64);
+            rng
+        })
+    }
+}
+
+impl Default for Rngs {
+    fn default() -> Self {
+        Self::new(0, Owner::Sim)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn streams() {
+        let mut a = Rngs::new(5, Owner::Sim);
+        let mut b = Rngs::new(5, Owner::Sim);
+
+        // Extra rolls in one stream don't move the others
+        for _ in 0..10 {
+            a.get(Stream::Proc, 1).gen::<u64>();
+        }
+        assert_eq!(a.get(Stream::Hit, 1).gen::<u64>(), b.get(Stream::Hit, 1).gen::<u64>());
+        assert_eq!(a.get(Stream::Proc, 2).gen::<u64>(), b.get(Stream::Proc, 2).gen::<u64>());
+
+        let mut c = Rngs::new(5, Owner::Sim);
+        assert_ne!(c.get(Stream::Hit, 1).gen::<u64>(), c.get(Stream::Hit, 2).gen::<u64>());
+        assert_ne!(c.get(Stream::Crit, 1).gen::<u64>(), c.get(Stream::Damage, 1).gen::<u64>());
+
+        // The sim and a unit with the same seed roll different numbers for the same stream and id
+        let mut sim = Rngs::new(5, Owner::Sim);
+        let mut unit = Rngs::new(5, Owner::Unit);
+        for stream in [Stream::Proc, Stream::Hit, Stream::Consumable] {
+            assert_ne!(sim.get(stream, 1).gen::<u64>(), unit.get(stream, 1).gen::<u64>());
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use crate::common;
+use crate::config::Config;
+use crate::sim;
+use crate::stats::Stat;
+use serde::{Serialize, Deserialize};
+
+// Varies one stat from `from` to `to` in `steps` points
+// The values are added to the gear stats unless is_absolute is set
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ScalingConfig {
+    pub stat: Stat,
+    pub from: f64,
+    pub to: f64,
+    pub steps: i32,
+    pub is_absolute: bool,
+    // All players when empty
+    pub player_ids: Vec<i32>,
+}
+
+impl Default for ScalingConfig {
+    fn default() -> Self {
+        Self {
+            stat: Stat::Sp,
+            from: 0.0,
+            to: 100.0,
+            steps: 5,
+            is_absolute: false,
+            player_ids: vec![],
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct ScalingPoint {
+    pub value: f64,
+    // Summed over the scaled players
+    pub dps: f64,
+    // Half width of the 95% confidence interval
+    pub dps_ci: f64,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct ScalingResult {
+    pub stat: Stat,
+    pub iterations: i32,
+    pub rng_seed: u64,
+    pub points: Vec<ScalingPoint>,
+}
+
+pub fn values(scaling: &ScalingConfig) -> Vec<f64> {
+    let steps = scaling.steps.max(1);
+    if steps == 1 {
+        return vec![scaling.from];
+    }
+
+    (0..steps).map(|i| scaling.from + (scaling.to - scaling.from) * i as f64 / (steps - 1) as f64).collect()
+}
+
+// All points use the same seeds, like profile sets
+pub fn run(mut config: Config, scaling: &ScalingConfig, iterations: i32) -> Result<ScalingResult, String> {
+    sim::fix_rng_seed(&mut config);
+    config.keep_iterations = true;
+
+    let mut result = ScalingResult { stat: scaling.stat, iterations, rng_seed: config.rng_seed, points: vec![] };
+    let is_scaled = |i: usize| scaling.player_ids.is_empty() || scaling.player_ids.contains(&(i as i32 + 1));
+
+    for value in values(scaling) {
+        let mut c = config.clone();
+        for (i, player) in c.players.iter_mut().enumerate() {
+            if is_scaled(i) {
+                let stat = player.stats.stat_mut(scaling.stat);
+                if scaling.is_absolute {
+                    *stat = value;
+                } else {
+                    *stat+= value;
+                }
+            }
+        }
+
+        let r = sim::run_multiple(c, iterations)?;
+        let dps: Vec<f64> = r.iteration_results.iter()
+            .map(|r| r.player_dps.iter().enumerate().filter(|(i, _)| is_scaled(*i)).map(|(_, dps)| dps).sum())
+            .collect();
+        let (dps, dps_ci) = common::mean_ci(&dps);
+        result.points.push(ScalingPoint { value, dps, dps_ci });
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::tests::fireball_config;
+
+    #[test]
+    fn steps() {
+        let scaling = ScalingConfig { stat: Stat::Hit, from: 0.0, to: 16.0, steps: 5, ..Default::default() };
+        assert_eq!(values(&scaling), vec![0.0, 4.0, 8.0, 12.0, 16.0]);
+        assert_eq!(values(&ScalingConfig { steps: 1, from: 3.0, ..Default::default() }), vec![3.0]);
+    }
+
+    #[test]
+    fn spell_power() {
+        let mut config = fireball_config(60.0);
+        config.players[0].stats.sp = 100.0;
+
+        let scaling = ScalingConfig { stat: Stat::Sp, from: 0.0, to: 300.0, steps: 3, ..Default::default() };
+        let result = run(config.clone(), &scaling, 20).unwrap();
+
+        assert_eq!(result.points.len(), 3);
+        assert!(result.points.windows(2).all(|p| p[1].dps > p[0].dps));
+        assert!(result.points.iter().all(|p| p.dps_ci > 0.0));
+
+        // Only the dps of the scaled player counts
+        config.players.push(config.players[0].clone());
+        let raid = run(config.clone(), &ScalingConfig { steps: 1, ..scaling.clone() }, 20).unwrap();
+        let single = run(config, &ScalingConfig { steps: 1, player_ids: vec![2], ..scaling }, 20).unwrap();
+        assert!(single.points[0].dps > 0.0);
+        assert!(single.points[0].dps < raid.points[0].dps * 0.75);
+    }
+}
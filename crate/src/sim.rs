@@ -2,11 +2,15 @@ use crate::aura;
 use crate::common;
 use crate::config::Config;
 use crate::cooldown;
+use crate::debuff;
 use crate::event::Event;
 use crate::event::EventType;
+use crate::factory;
 use crate::log;
 use crate::macros::console_log;
-use crate::mage::Mage;
+use crate::rng::Owner;
+use crate::rng::Rngs;
+use crate::rng::Stream;
 use crate::spell;
 use crate::target::Target;
 use crate::unit::Unit;
@@ -23,24 +27,125 @@ pub fn new_rng(rng_seed: u64) -> ChaCha8Rng {
     ChaCha8Rng::from_entropy()
 }
 
+// Runs that are compared need the same seeds, unseeded configs get a random one
+pub fn fix_rng_seed(config: &mut Config) {
+    if config.rng_seed == 0 {
+        config.rng_seed = new_rng(0).gen_range(1..u32::MAX as u64);
+    }
+}
+
 #[derive(Default, Serialize, Deserialize, Clone)]
 pub struct PlayerResult {
+    pub class: common::Class,
     pub dmg: u64,
     pub dps: f64,
     pub ignite_dmg: u64,
     pub ignite_dps: f64,
+    pub threat: f64,
+    pub tps: f64,
+    pub name: String,
+    pub mana: ManaResult,
+}
+
+// How a player used their mana
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct ManaResult {
+    // First time the player had to wait for mana, the mean of the runs where it happened
+    pub t_oom: Option<f64>,
+    // Fraction of the runs where the player ran out of mana
+    pub oom_rate: f64,
+    // Number of those runs, the t_oom mean is taken over them
+    pub oom_runs: u32,
+    pub t_waiting: f64,
+    pub spent: f64,
+    pub dmg_per_mana: f64,
+    // Mana lost because the player was already at max mana
+    pub wasted: f64,
+    pub spells: HashMap<String, f64>,
+    pub sources: HashMap<String, f64>,
+}
+
+impl ManaResult {
+    // Adds run number n to the means
+    fn add_run(&mut self, run: &ManaResult, n: f64) {
+        if let Some(t) = run.t_oom {
+            self.oom_runs+= 1;
+            let mean = self.t_oom.unwrap_or(0.0);
+            self.t_oom = Some(mean + (t - mean) / self.oom_runs as f64);
+        }
+
+        self.oom_rate+= (run.oom_rate - self.oom_rate) / n;
+        self.t_waiting+= (run.t_waiting - self.t_waiting) / n;
+        self.spent+= (run.spent - self.spent) / n;
+        self.dmg_per_mana+= (run.dmg_per_mana - self.dmg_per_mana) / n;
+        self.wasted+= (run.wasted - self.wasted) / n;
+        mean_map(&mut self.spells, &run.spells, n);
+        mean_map(&mut self.sources, &run.sources, n);
+    }
+}
+
+// Keys missing from a run count as 0 for it
+fn mean_map(mean: &mut HashMap<String, f64>, run: &HashMap<String, f64>, n: f64) {
+    for key in run.keys() {
+        mean.entry(key.clone()).or_insert(0.0);
+    }
+    for (key, value) in mean.iter_mut() {
+        *value+= (run.get(key).unwrap_or(&0.0) - *value) / n;
+    }
+}
+
+// Player values for each timeline bucket
+// Damage is done within the bucket, mana is sampled at the start of it
+// Oom is the fraction of runs where the player had to wait for mana in the bucket
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct PlayerTimeline {
     pub name: String,
+    pub dmg: Vec<f64>,
+    pub mana: Vec<f64>,
+    pub mana_percent: Vec<f64>,
+    pub oom: Vec<f64>,
+}
+
+impl PlayerTimeline {
+    fn push(&mut self, mana: f64, mana_percent: f64) {
+        self.dmg.push(0.0);
+        self.mana.push(mana);
+        self.mana_percent.push(mana_percent);
+        self.oom.push(0.0);
+    }
+}
+
+// The numbers of one iteration, seed is 0 when the config isn't seeded
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct IterationResult {
+    pub iteration: i32,
+    pub seed: u64,
+    pub duration: f64,
+    pub dps: f64,
+    pub ignite_dps: f64,
+    pub player_dps: Vec<f64>,
+}
+
+// An iteration that can be run again with replay()
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct IterationRef {
+    pub iteration: i32,
+    pub seed: u64,
+    pub dps: f64,
 }
 
 // Result from one run
 #[derive(Default, Serialize, Deserialize, Clone)]
 pub struct SimulationResult {
+    pub iteration: i32,
+    pub seed: u64,
     pub t: f64,
     pub dmg: u64,
     pub dps: f64,
     pub ignite_dmg: u64,
     pub ignite_dps: f64,
     pub players: Vec<PlayerResult>,
+    pub timelines: Vec<PlayerTimeline>,
     pub log: Vec<log::LogEntry>,
 }
 
@@ -48,35 +153,75 @@ pub struct SimulationResult {
 #[derive(Default, Serialize, Deserialize)]
 pub struct SimulationsResult {
     pub iterations: i32,
+    pub rng_seed: u64,
     pub dps: f64,
     pub min_dps: f64,
     pub max_dps: f64,
+    pub min_iteration: IterationRef,
+    pub max_iteration: IterationRef,
+    pub median_iteration: IterationRef,
     pub ignite_dps: f64,
     pub players: Vec<PlayerResult>,
     pub histogram: HashMap<u32, u32>,
     pub ignite_histogram: HashMap<u32, u32>,
+    pub timeline_bucket: f64,
+    pub timelines: Vec<PlayerTimeline>,
+    pub iteration_results: Vec<IterationResult>,
 }
 
 // Public function to start a single simulation
-pub fn run_single(config: Config) -> SimulationResult {
+pub fn run_single(config: Config) -> Result<SimulationResult, String> {
+    replay(config, 1)
+}
+
+// Runs one iteration of run_multiple() again with the log enabled
+// The config needs the rng seed that run_multiple() returned
+pub fn replay(config: Config, iteration: i32) -> Result<SimulationResult, String> {
+    factory::check_config(&config)?;
+
     let mut sim = Sim::new(config);
-    sim.iteration = 1;
+    sim.iteration = iteration;
     sim.log_enabled = true;
 
-    sim.run()
+    Ok(sim.run())
 }
 
 // Public function to start multiple simulations
-pub fn run_multiple(config: Config, iterations: i32) -> SimulationsResult {
+pub fn run_multiple(mut config: Config, iterations: i32) -> Result<SimulationsResult, String> {
+    factory::check_config(&config)?;
+
+    // Every iteration should be possible to replay
+    fix_rng_seed(&mut config);
+
     let mut sim = Sim::new(config);
     sim.log_enabled = false;
 
-    let mut result: SimulationsResult = SimulationsResult { iterations, ..Default::default() };
+    let mut result: SimulationsResult = SimulationsResult {
+        iterations,
+        rng_seed: sim.config.rng_seed,
+        timeline_bucket: sim.config.timeline_bucket,
+        ..Default::default()
+    };
+    let mut runs: Vec<IterationRef> = vec![];
     let bin_size: f64 = 50.0;
+    // Runs that reached each timeline bucket, they differ with duration variance
+    let mut bucket_runs: Vec<f64> = vec![];
 
     for i in 1..=iterations {
         sim.iteration = i;
         let r = sim.run();
+        runs.push(IterationRef { iteration: i, seed: r.seed, dps: r.dps });
+
+        if sim.config.keep_iterations {
+            result.iteration_results.push(IterationResult {
+                iteration: i,
+                seed: r.seed,
+                duration: r.t,
+                dps: r.dps,
+                ignite_dps: r.ignite_dps,
+                player_dps: r.players.iter().map(|p| p.dps).collect(),
+            });
+        }
 
         result.dps+= (r.dps - result.dps) / (i as f64);
         result.ignite_dps+= (r.ignite_dps - result.ignite_dps) / (i as f64);
@@ -108,26 +253,45 @@ pub fn run_multiple(config: Config, iterations: i32) -> SimulationsResult {
             for (j, pr) in r.players.iter().enumerate() {
                 result.players[j].dps+= (pr.dps - result.players[j].dps) / (i as f64);
                 result.players[j].ignite_dps+= (pr.ignite_dps - result.players[j].ignite_dps) / (i as f64);
+                result.players[j].tps+= (pr.tps - result.players[j].tps) / (i as f64);
+                result.players[j].mana.add_run(&pr.mana, i as f64);
             }
         }
-    }
 
-    result
-}
+        for (j, timeline) in r.timelines.iter().enumerate() {
+            if result.timelines.len() <= j {
+                result.timelines.push(PlayerTimeline { name: timeline.name.clone(), ..Default::default() });
+            }
+            let mean = &mut result.timelines[j];
 
-fn spawn_player(config: Config, id: i32) -> Box<dyn Unit> {
-    let mut player = Box::new(Mage::new());
-    let index = (id as usize) - 1;
+            for b in 0..timeline.dmg.len() {
+                if mean.dmg.len() <= b {
+                    mean.push(0.0, 0.0);
+                }
+                if bucket_runs.len() <= b {
+                    bucket_runs.push(0.0);
+                }
+                if j == 0 {
+                    bucket_runs[b]+= 1.0;
+                }
 
-    player.id = id;
-    player.name.clone_from(&config.players[index].name);
-    player.level = config.players[index].level;
-    player.stats = config.players[index].stats;
+                let n = bucket_runs[b];
+                mean.dmg[b]+= (timeline.dmg[b] - mean.dmg[b]) / n;
+                mean.mana[b]+= (timeline.mana[b] - mean.mana[b]) / n;
+                mean.mana_percent[b]+= (timeline.mana_percent[b] - mean.mana_percent[b]) / n;
+                mean.oom[b]+= (timeline.oom[b] - mean.oom[b]) / n;
+            }
+        }
+    }
 
-    player.set_config(config);
-    player.reset();
+    runs.sort_by(|a, b| a.dps.total_cmp(&b.dps));
+    if let (Some(min), Some(max)) = (runs.first(), runs.last()) {
+        result.min_iteration = min.clone();
+        result.max_iteration = max.clone();
+        result.median_iteration = runs[runs.len() / 2].clone();
+    }
 
-    player
+    Ok(result)
 }
 
 // Main sim struct
@@ -137,11 +301,13 @@ pub struct Sim {
     pub t: f64,
     pub duration: f64,
     pub iteration: i32,
-    pub rng: ChaCha8Rng,
+    pub rngs: Rngs,
     pub units: HashMap<i32, Box<dyn Unit>>,
     pub targets: HashMap<i32, Target>,
     pub log_enabled: bool,
     pub log: Vec<log::LogEntry>,
+    pub timelines: Vec<PlayerTimeline>,
+    pub mana_results: Vec<ManaResult>,
     pub result: SimulationResult,
 }
 
@@ -154,11 +320,13 @@ impl Sim {
             t: 0.0,
             duration: 0.0,
             iteration: 1,
-            rng: new_rng(0),
+            rngs: Rngs::default(),
             units: HashMap::new(),
             targets: HashMap::new(),
             log_enabled: false,
             log: vec![],
+            timelines: vec![],
+            mana_results: vec![],
             result: SimulationResult::default(),
         }
     }
@@ -166,6 +334,8 @@ impl Sim {
     // The internal function to start a simulation
     pub fn run(&mut self) -> SimulationResult {
         self.reset();
+        self.sample_timelines(0.0);
+        self.push_debuffs();
 
         for i in 0..self.config.players.len() {
             let id = (i as i32) + 1;
@@ -177,23 +347,40 @@ impl Sim {
             }
         }
 
+        for i in 0..self.config.supports.len() {
+            self.next_event((self.config.players.len() + i) as i32 + 1);
+        }
+
         self.work();
+        self.sample_timelines(self.duration);
 
         self.result.dmg = self.total_dmg();
         self.result.dps = (self.result.dmg as f64) / self.result.t;
         self.result.ignite_dmg = self.total_ignite_dmg();
         self.result.ignite_dps = (self.result.ignite_dmg as f64) / self.result.t;
         self.result.log = self.log.clone();
+        self.result.timelines = std::mem::take(&mut self.timelines);
 
         for i in 1..=self.config.players.len() {
             let dmg = self.unit_total_dmg(i as i32);
             let ignite_dmg = self.unit_total_ignite_dmg(i as i32);
+            let threat = self.unit_total_threat(i as i32);
+            let mut mana = std::mem::take(&mut self.mana_results[i - 1]);
+            mana.oom_runs = mana.t_oom.is_some() as u32;
+            mana.oom_rate = mana.oom_runs as f64;
+            if mana.spent > 0.0 {
+                mana.dmg_per_mana = dmg as f64 / mana.spent;
+            }
             self.result.players.push(PlayerResult {
+                class: self.config.players[i - 1].class,
                 name: self.units[&(i as i32)].name(),
                 dmg,
                 dps: (dmg as f64) / self.result.t,
                 ignite_dmg,
                 ignite_dps: (ignite_dmg as f64) / self.result.t,
+                threat,
+                tps: threat / self.result.t,
+                mana,
             });
         }
 
@@ -203,33 +390,88 @@ impl Sim {
     fn reset(&mut self) {
         let rng_seed = self.config.rng_seed + (self.iteration as u64) - 1;
         if self.config.rng_seed != 0 {
-            self.rng = new_rng(rng_seed);
+            self.rngs = Rngs::new(rng_seed, Owner::Sim);
         }
 
         self.t = 0.0;
-        self.duration = self.config.duration - self.config.duration_variance + self.rng.gen_range(0.0..=self.config.duration_variance) * 2.0;
+        self.duration = self.config.duration - self.config.duration_variance + self.rngs.get(Stream::Duration, 0).gen_range(0.0..=self.config.duration_variance) * 2.0;
         self.result = SimulationResult::default();
         self.result.t = self.duration;
+        self.result.iteration = self.iteration;
+        if self.config.rng_seed != 0 {
+            self.result.seed = rng_seed;
+        }
 
         self.queue.clear();
 
         self.units.clear();
         for i in 1..=self.config.players.len() {
             let id = i as i32;
-            self.units.insert(id, spawn_player(self.config.clone(), id));
+            self.units.insert(id, factory::spawn_player(self.config.clone(), id));
             if self.config.rng_seed != 0 {
                 self.units.get_mut(&id).unwrap().new_rng(rng_seed);
             }
         }
+        for i in 0..self.config.supports.len() {
+            let support = factory::spawn_support_unit(self.config.clone(), i);
+            self.units.insert(support.id(), support);
+        }
 
         self.targets.clear();
         for i in 1..=self.config.targets {
-            self.targets.insert(i, Target::new(i));
+            let mut target = Target::new(i);
+            target.max_health = self.config.target_health;
+            self.targets.insert(i, target);
         }
 
         if self.log_enabled {
             self.log.clear();
         }
+
+        self.mana_results = vec![ManaResult::default(); self.config.players.len()];
+
+        self.timelines.clear();
+        if self.config.timeline_bucket > 0.0 {
+            for i in 1..=self.config.players.len() {
+                self.timelines.push(PlayerTimeline { name: self.units[&(i as i32)].name(), ..Default::default() });
+            }
+        }
+    }
+
+    // The debuffs from the config, including the old debuff flags
+    fn debuff_configs(&self) -> Vec<debuff::DebuffConfig> {
+        let mut debuffs = self.config.debuffs.clone();
+
+        for (enabled, d) in [
+            (self.config.curse_of_elements, debuff::Debuff::CurseOfElements),
+            (self.config.curse_of_shadows, debuff::Debuff::CurseOfShadows),
+            (self.config.judgement_of_wisdom, debuff::Debuff::JudgementOfWisdom),
+        ] {
+            if enabled && !debuffs.iter().any(|c| c.debuff == d) {
+                debuffs.push(debuff::DebuffConfig::new(d));
+            }
+        }
+
+        debuffs
+    }
+
+    // Debuffs are applied by the environment (unit 0) to every target
+    fn push_debuffs(&mut self) {
+        for config in self.debuff_configs() {
+            for (t, duration) in debuff::applications(&config, self.duration, self.rngs.get(Stream::Debuff, 0)) {
+                for target_id in 1..=self.config.targets {
+                    let mut aura = debuff::aura(config.debuff);
+                    aura.duration = duration;
+
+                    let mut event = Event::new(EventType::AuraGain);
+                    event.t = t;
+                    event.target_id = target_id;
+                    event.aura = Some(aura);
+                    event.is_main_event = false;
+                    self.push_event(event);
+                }
+            }
+        }
     }
 
     fn unit(&self, unit_id: i32) -> &dyn Unit {
@@ -256,6 +498,10 @@ impl Sim {
         self.targets.iter().fold(0, |acc, (id, t)| acc + t.unit_ignite_dmg.get(&unit_id).unwrap_or(&0))
     }
 
+    fn unit_total_threat(&self, unit_id: i32) -> f64 {
+        self.targets.iter().fold(0.0, |acc, (id, t)| acc + t.unit_threat.get(&unit_id).unwrap_or(&0.0))
+    }
+
     fn work(&mut self) {
         while !self.queue.is_empty() {
             let mut event = self.queue.remove(0);
@@ -265,11 +511,38 @@ impl Sim {
                 break;
             }
 
+            self.sample_timelines(event.t);
             self.t = event.t;
             self.handle_event(&mut event);
         }
     }
 
+    // Starts the timeline buckets up to t with the current mana of the players
+    fn sample_timelines(&mut self, t: f64) {
+        let size = self.config.timeline_bucket;
+        if self.timelines.is_empty() {
+            return;
+        }
+
+        while (self.timelines[0].mana.len() as f64) * size <= t.min(self.duration - 0.001) {
+            for (i, timeline) in self.timelines.iter_mut().enumerate() {
+                let unit = &self.units[&(i as i32 + 1)];
+                timeline.push(unit.current_mana(), unit.mana_percent());
+            }
+        }
+    }
+
+    fn timeline_bucket(&mut self, unit_id: i32) -> Option<(&mut PlayerTimeline, usize)> {
+        let bucket = (self.t / self.config.timeline_bucket) as usize;
+        let timeline = self.timelines.get_mut((unit_id as usize).wrapping_sub(1))?;
+
+        if bucket < timeline.dmg.len() {
+            Some((timeline, bucket))
+        } else {
+            None
+        }
+    }
+
     fn handle_events(&mut self, events: Vec<Event>) {
         for mut event in events {
             if event.t == 0.0 {
@@ -288,7 +561,8 @@ impl Sim {
                 if self.can_cast(event.unit_id, spell) {
                     self.on_cast_start(event);
                 } else if event.is_main_event {
-                    self.wait(event.unit_id, 0.5, format!("Not enough mana (needed {})", spell.this_mana_cost));
+                    let cost = spell.this_mana_cost;
+                    self.wait_for_mana(event.unit_id, cost);
                 }
             }
 
@@ -299,7 +573,8 @@ impl Sim {
                 if self.can_cast(event.unit_id, spell) {
                     self.on_cast_success(event);
                 } else if event.is_main_event {
-                    self.wait(event.unit_id, 0.5, format!("Not enough mana (needed {})", spell.this_mana_cost));
+                    let cost = spell.this_mana_cost;
+                    self.wait_for_mana(event.unit_id, cost);
                 }
             }
 
@@ -336,8 +611,10 @@ impl Sim {
             }
 
             EventType::Wait => {
-                if !event.text.is_empty() {
-                    self.log_value(log::LogType::Wait, event.text.clone(), event.unit_id, event.t);
+                if !event.text.is_empty() && self.log_enabled {
+                    let mut entry = self.log_event(log::LogType::Wait, event, event.t);
+                    entry.text.clone_from(&event.text);
+                    self.log_push(entry);
                 }
 
                 let mut ev = Event::new(EventType::Idle);
@@ -451,6 +728,41 @@ impl Sim {
         self.handle_event(&mut event);
     }
 
+    // The unit can't afford its spell and tries again a bit later
+    fn wait_for_mana(&mut self, unit_id: i32, cost: f64) {
+        let t: f64 = 0.5;
+        let (now, t_left) = (self.t, self.duration - self.t);
+
+        if let Some(mana) = self.mana_result(unit_id) {
+            mana.t_oom.get_or_insert(now);
+            mana.t_waiting+= t.min(t_left);
+        }
+        if let Some((timeline, b)) = self.timeline_bucket(unit_id) {
+            timeline.oom[b] = 1.0;
+        }
+
+        self.wait(unit_id, t, format!("Not enough mana (needed {})", cost));
+    }
+
+    fn mana_result(&mut self, unit_id: i32) -> Option<&mut ManaResult> {
+        self.mana_results.get_mut((unit_id as usize).wrapping_sub(1))
+    }
+
+    // Tracks a mana change that was clamped by the unit
+    fn add_mana_result(&mut self, unit_id: i32, source: &str, mana: f64, mana_before: f64) {
+        let mana_after = self.unit(unit_id).current_mana();
+
+        if let Some(result) = self.mana_result(unit_id) {
+            if mana < 0.0 {
+                result.spent+= mana_before - mana_after;
+                *result.spells.entry(source.to_string()).or_insert(0.0)+= mana_before - mana_after;
+            } else {
+                result.wasted+= mana - (mana_after - mana_before);
+                *result.sources.entry(source.to_string()).or_insert(0.0)+= mana_after - mana_before;
+            }
+        }
+    }
+
     fn can_cast(&mut self, unit_id: i32, spell: &spell::Spell) -> bool {
         self.unit(unit_id).current_mana() >= spell.this_mana_cost
     }
@@ -470,12 +782,9 @@ impl Sim {
 
         let spell = event.spell.as_ref().unwrap();
 
-        if !spell.is_hidden() {
-            let mut log_text = format!("s[{}]", spell.name);
-            if event.target_id != 0 {
-                log_text.push_str(&format!(" -> t[{}]", self.target(event.target_id).name));
-            }
-            self.log_value(log::LogType::CastStart, log_text, event.unit_id, spell.this_cast_time);
+        if !spell.is_hidden() && self.log_enabled {
+            let entry = self.log_event(log::LogType::CastStart, event, spell.this_cast_time);
+            self.log_push(entry);
         }
 
         // Set unit gcd
@@ -505,13 +814,19 @@ impl Sim {
 
         // Apply mana cost and cooldown
         let unit = self.units.get_mut(&event.unit_id).unwrap();
+        let mana_before = unit.current_mana();
         unit.mod_mana(-spell.this_mana_cost, self.t);
+        if spell.this_mana_cost > 0.0 {
+            self.add_mana_result(event.unit_id, &spell.name, -spell.this_mana_cost, mana_before);
+        }
         if spell.cooldown > 0.0 {
             self.add_spell_cooldown(event.unit_id, spell);
         }
 
-        if !spell.is_hidden() {
-            self.log(log::LogType::CastSuccess, format!("s[{}]", spell.name), event.unit_id);
+        if !spell.is_hidden() && self.log_enabled {
+            let mut entry = self.log_event(log::LogType::CastSuccess, event, 0.0);
+            entry.mana_before = mana_before;
+            self.log_push(entry);
         }
 
         if spell.is_trigger {
@@ -522,6 +837,13 @@ impl Sim {
             for i in 1..=spell.ticks {
                 self.push_channeling_tick(event.unit_id, spell, event.target_id, i, 0.0);
             }
+        } else if spell.is_aoe {
+            // Aoe spells hit every target
+            let mut target_ids: Vec<i32> = self.targets.keys().copied().collect();
+            target_ids.sort();
+            for target_id in target_ids {
+                self.push_spell_impact(event.unit_id, spell, target_id, spell.travel_time(self.config.distance as f64));
+            }
         } else {
             self.push_spell_impact(event.unit_id, spell, event.target_id, spell.travel_time(self.config.distance as f64));
         }
@@ -555,27 +877,40 @@ impl Sim {
         }
 
         if instance.dmg > 0.0 && event.unit_id != 0 {
+            let threat = instance.dmg.round() * self.unit(event.unit_id).spell_threat_multiplier(&instance.spell);
+            self.targets.get_mut(&event.target_id).expect("TARGET_NOT_FOUND").add_threat(event.unit_id, threat);
             self.targets.get_mut(&event.target_id).expect("TARGET_NOT_FOUND").add_dmg(event.unit_id, instance.dmg.round() as u64);
             if instance.spell.id == spell::IGNITE {
                 self.targets.get_mut(&event.target_id).expect("TARGET_NOT_FOUND").add_ignite_dmg(event.unit_id, instance.dmg.round() as u64);
             }
+            let dmg = instance.dmg.round();
+            if let Some((timeline, b)) = self.timeline_bucket(event.unit_id) {
+                timeline.dmg[b]+= dmg;
+            }
+
+            // Dots benefit from Improved Shadow Bolt without using up its charges
+            if instance.spell.school == common::School::Shadow && !instance.spell.is_dot {
+                self.use_shadow_vulnerability_charge(event.target_id);
+            }
         }
 
         let inst = event.spell_instance.as_ref().unwrap();
         if inst.spell.min_dmg > 0.0 || inst.result == spell::SpellResult::Miss {
-            self.log_spell_impact(event.unit_id, inst, event.target_id);
+            let entry = self.log_event(log::LogType::SpellImpact, event, inst.dmg);
+            self.log_push(entry);
         }
 
-        if self.config.judgement_of_wisdom {
+        if self.target(event.target_id).auras.has_any(aura::JUDGEMENT_OF_WISDOM) {
             let mut chance = 50.0;
             if inst.spell.is_channeled {
                 chance/= inst.spell.ticks as f64;
             }
-            let roll = self.rng.gen_range(0.0..=100.0);
+            let roll = self.rngs.get(Stream::Proc, event.unit_id).gen_range(0.0..=100.0);
             if roll < chance {
                 let mut ev = Event::new(EventType::ManaGain);
                 ev.unit_id = event.unit_id;
                 ev.mana = 59.0;
+                ev.text = String::from("Judgement of Wisdom");
                 self.on_mana_gain(&mut ev);
             }
         }
@@ -586,6 +921,23 @@ impl Sim {
         // TODO: spell logging
     }
 
+    fn use_shadow_vulnerability_charge(&mut self, target_id: i32) {
+        let auras = &mut self.targets.get_mut(&target_id).expect("TARGET_NOT_FOUND").auras;
+        let Some(aura) = auras.get_aura(aura::SHADOW_VULNERABILITY, 0).cloned() else {
+            return;
+        };
+
+        if aura.stacks > 1 {
+            auras.add_stacks(aura.id, aura.owner_id, -1);
+        } else {
+            let mut expire = Event::new(EventType::AuraExpire);
+            expire.unit_id = aura.owner_id;
+            expire.target_id = target_id;
+            expire.aura = Some(aura);
+            self.on_aura_expire(&mut expire);
+        }
+    }
+
     fn on_spell_tick(&mut self, event: &mut Event) {
         if event.spell_instance.is_none() {
             return;
@@ -612,12 +964,18 @@ impl Sim {
             return;
         }
 
-        self.units.get_mut(&event.unit_id).unwrap().mod_mana(event.mana, self.t);
+        let unit = self.units.get_mut(&event.unit_id).unwrap();
+        let mana_before = unit.current_mana();
+        unit.mod_mana(event.mana, self.t);
+
+        let source = if event.text.is_empty() { "Mana" } else { event.text.as_str() };
+        self.add_mana_result(event.unit_id, source, event.mana, mana_before);
 
-        if event.text.is_empty() {
-            self.log_value(log::LogType::Mana, String::from("Mana"), event.unit_id, event.mana);
-        } else {
-            self.log_value(log::LogType::Mana, event.text.clone(), event.unit_id, event.mana);
+        if self.log_enabled {
+            let mut entry = self.log_event(log::LogType::Mana, event, event.mana);
+            entry.mana_source = String::from(source);
+            entry.mana_before = mana_before;
+            self.log_push(entry);
         }
     }
 
@@ -626,8 +984,25 @@ impl Sim {
         let mana = (unit.mana_per_second(self.t) * crate::MANA_TICK_T).round();
 
         if mana > 0.0 {
+            let mana_before = unit.current_mana();
             unit.mod_mana(mana, self.t);
-            self.log_value(log::LogType::Mana, String::from("Mana regen"), event.unit_id, mana);
+
+            // Evocation and innervate work through regen
+            let source = if unit.auras().has_any(aura::EVOCATION) {
+                "Evocation"
+            } else if unit.auras().has_any(aura::INNERVATE) {
+                "Innervate"
+            } else {
+                "Regen"
+            };
+            self.add_mana_result(event.unit_id, source, mana, mana_before);
+
+            if self.log_enabled {
+                let mut entry = self.log_event(log::LogType::Mana, event, mana);
+                entry.mana_source = String::from(source);
+                entry.mana_before = mana_before;
+                self.log_push(entry);
+            }
         }
 
         self.push_mana_regen(event.unit_id);
@@ -655,7 +1030,7 @@ impl Sim {
 
         if old_stacks < 1 || aura.stack_refresh {
             aura.t_expires = self.t + aura.duration;
-            self.remove_aura_expiration(event.unit_id, aura.id, event.target_id);
+            self.remove_aura_expiration(event.unit_id, aura, event.target_id);
 
             let mut expire = Event::new(EventType::AuraExpire);
             expire.unit_id = event.unit_id;
@@ -667,20 +1042,17 @@ impl Sim {
         }
 
         if stacks != old_stacks || aura.show_refresh {
-            let events = self.units.get_mut(&event.unit_id).unwrap().on_event(event);
-            self.handle_events(events);
-
-            let a = event.aura.as_ref().unwrap();
-            if event.target_id != 0 {
-                if a.max_stacks > 1 {
-                    self.log(log::LogType::AuraGain, format!("a[{}] ({}) -> t[{}]", a.name, stacks, self.target(event.target_id).name), event.unit_id);
-                } else {
-                    self.log(log::LogType::AuraGain, format!("a[{}] -> t[{}]", a.name, self.target(event.target_id).name), event.unit_id);
+            if event.unit_id != 0 {
+                let events = self.units.get_mut(&event.unit_id).unwrap().on_event(event);
+                self.handle_events(events);
+            }
+
+            if self.log_enabled {
+                let mut entry = self.log_event(log::LogType::AuraGain, event, 0.0);
+                if event.aura.as_ref().unwrap().max_stacks > 1 {
+                    entry.stacks = stacks;
                 }
-            } else if a.max_stacks > 1 {
-                self.log(log::LogType::AuraGain, format!("a[{}] ({})", a.name, stacks), event.unit_id);
-            } else {
-                self.log(log::LogType::AuraGain, format!("a[{}]", a.name), event.unit_id);
+                self.log_push(entry);
             }
         }
     }
@@ -699,19 +1071,20 @@ impl Sim {
 
         if auras.has(aura.id, aura.owner_id) {
             auras.remove(aura.id, aura.owner_id);
-            self.remove_aura_expiration(event.unit_id, aura.id, event.target_id);
+            self.remove_aura_expiration(event.unit_id, aura, event.target_id);
 
             if aura.id == spell::IGNITE && event.target_id > 0 {
                 self.targets.get_mut(&event.target_id).unwrap().reset_ignite();
             }
 
-            let events = self.units.get_mut(&event.unit_id).unwrap().on_event(event);
-            self.handle_events(events);
+            if event.unit_id != 0 {
+                let events = self.units.get_mut(&event.unit_id).unwrap().on_event(event);
+                self.handle_events(events);
+            }
 
-            if event.target_id != 0 {
-                self.log(log::LogType::AuraExpire, format!("a[{}] != t[{}]", aura.name, self.target(event.target_id).name), event.unit_id);
-            } else {
-                self.log(log::LogType::AuraExpire, format!("a[{}]", aura.name), event.unit_id);
+            if self.log_enabled {
+                let entry = self.log_event(log::LogType::AuraExpire, event, 0.0);
+                self.log_push(entry);
             }
         }
     }
@@ -745,8 +1118,9 @@ impl Sim {
         self.handle_events(events);
 
         let c = event.cooldown.as_ref().unwrap();
-        if !c.is_hidden {
-            self.log(log::LogType::CooldownGain, format!("c[{}]", c.name), event.unit_id);
+        if !c.is_hidden && self.log_enabled {
+            let entry = self.log_event(log::LogType::CooldownGain, event, 0.0);
+            self.log_push(entry);
         }
     }
 
@@ -762,8 +1136,9 @@ impl Sim {
             cooldowns.remove(cooldown.id);
             self.remove_cooldown_expiration(event.unit_id, cooldown.id);
 
-            if !cooldown.is_hidden {
-                self.log(log::LogType::CooldownExpire, format!("c[{}]", cooldown.name), event.unit_id);
+            if !cooldown.is_hidden && self.log_enabled {
+                let entry = self.log_event(log::LogType::CooldownExpire, event, 0.0);
+                self.log_push(entry);
             }
         }
     }
@@ -774,6 +1149,7 @@ impl Sim {
         event.unit_id = unit_id;
         event.t = self.t;
         event.cooldown = Some(cooldown::Cooldown::new(spell.id, spell.name.clone(), spell.cooldown));
+        event.cooldown.as_mut().unwrap().school = spell.school;
         self.on_cooldown_gain(&mut event);
     }
 
@@ -785,8 +1161,10 @@ impl Sim {
         self.queue.retain(|ev| ev.event_type != EventType::SpellTick || ev.unit_id != unit_id || ev.target_id != target_id || ev.spell_instance.as_ref().is_none() || ev.spell_instance.as_ref().unwrap().spell.id != spell_id);
     }
 
-    fn remove_aura_expiration(&mut self, unit_id: i32, id: i32, target_id: i32) {
-        self.queue.retain(|ev| ev.event_type != EventType::AuraExpire || ev.unit_id != unit_id || ev.target_id != target_id || ev.aura.as_ref().is_none() || ev.aura.as_ref().unwrap().id != id);
+    // Shared debuffs are one aura on the target, no matter which unit applied them last
+    fn remove_aura_expiration(&mut self, unit_id: i32, aura: &aura::Aura, target_id: i32) {
+        let any_unit = aura.is_shared && target_id != 0;
+        self.queue.retain(|ev| ev.event_type != EventType::AuraExpire || (!any_unit && ev.unit_id != unit_id) || ev.target_id != target_id || ev.aura.as_ref().is_none() || ev.aura.as_ref().unwrap().id != aura.id);
     }
 
     fn remove_cooldown_expiration(&mut self, unit_id: i32, id: i32) {
@@ -838,7 +1216,7 @@ impl Sim {
                 instance.result = spell::SpellResult::Hit;
                 instance.tick = i;
                 instance.dmg = dmg;
-                instance.resist = self.spell_dmg_resist(uid, &instance);
+                instance.resist = self.spell_dmg_resist(uid, &instance, target_id);
                 instance.dmg-= instance.resist;
                 self.push_event(Event {
                     event_type: EventType::SpellImpact,
@@ -913,24 +1291,29 @@ impl Sim {
                 instance.dmg*= self.spell_crit_dmg_multiplier(unit_id, &instance.spell, target_id);
             }
 
-            instance.resist = self.spell_dmg_resist(unit_id, instance);
+            instance.resist = self.spell_dmg_resist(unit_id, instance, target_id);
             instance.dmg-= instance.resist;
             instance.dmg = instance.dmg.round();
         }
     }
 
     fn roll_spell_result(&mut self, unit_id: i32, instance: &mut spell::SpellInstance, target_id: i32) -> spell::SpellResult {
-        if instance.spell.can_miss && self.rng.gen_range(0.0..=100.0) > self.spell_hit_chance(unit_id, &instance.spell, target_id) {
-            return spell::SpellResult::Miss;
+        if instance.spell.can_miss {
+            let roll = self.rngs.get(Stream::Hit, unit_id).gen_range(0.0..=100.0);
+            if roll > self.spell_hit_chance(unit_id, &instance.spell, target_id, instance.spell.is_binary) {
+                // Rolls that only fail because of the resistance of binary spells are resists
+                instance.is_resisted = roll <= self.spell_hit_chance(unit_id, &instance.spell, target_id, false);
+                return spell::SpellResult::Miss;
+            }
         }
-        if instance.spell.can_crit && self.rng.gen_range(0.0..=100.0) <= self.spell_crit_chance(unit_id, &instance.spell, target_id) {
+        if instance.spell.can_crit && self.rngs.get(Stream::Crit, unit_id).gen_range(0.0..=100.0) <= self.spell_crit_chance(unit_id, &instance.spell, target_id) {
             return spell::SpellResult::Crit;
         }
 
         spell::SpellResult::Hit
     }
 
-    fn spell_hit_chance(&mut self, unit_id: i32, spell: &spell::Spell, target_id: i32) -> f64 {
+    fn spell_hit_chance(&mut self, unit_id: i32, spell: &spell::Spell, target_id: i32, with_resistance: bool) -> f64 {
         let dlevel = (self.config.target_level - self.unit(unit_id).level()) as f64;
         let mut hit: f64 = 96.0 - dlevel;
 
@@ -942,8 +1325,8 @@ impl Sim {
         // Based on targets non-level based resistance
         // Chance of resist is 75% of the resistScore/resistCap
         // https://royalgiraffe.github.io/resist-guide
-        if spell.is_binary {
-            hit*= 1.0 - 0.75 * self.spell_resist_score(unit_id, spell, false) / ((self.config.target_level as f64) * 5.0);
+        if with_resistance {
+            hit*= 1.0 - 0.75 * self.spell_resist_score(unit_id, spell, target_id, false) / ((self.config.target_level as f64) * 5.0);
         }
 
         hit+= self.unit(unit_id).spell_hit_chance(spell);
@@ -958,6 +1341,10 @@ impl Sim {
             crit+= 2.0 * self.target(target_id).auras.stacks(aura::WINTERS_CHILL, 0) as f64;
         }
 
+        if self.target(target_id).is_frozen() {
+            crit+= self.unit(unit_id).spell_crit_chance_frozen(spell);
+        }
+
         crit = crit.min(100.0);
 
         // Crit suppression
@@ -979,7 +1366,7 @@ impl Sim {
         if self.config.avg_spell_dmg {
             dmg = (spell.min_dmg + spell.max_dmg) / 2.0;
         } else {
-            dmg = self.rng.gen_range(spell.min_dmg..=spell.max_dmg);
+            dmg = self.rngs.get(Stream::Damage, unit_id).gen_range(spell.min_dmg..=spell.max_dmg);
         }
 
         if spell.coeff > 0.0 {
@@ -1008,9 +1395,21 @@ impl Sim {
             dmg*= 1.0 + 0.03 * auras.stacks(aura::FIRE_VULNERABILITY, 0) as f64;
         }
 
-        if self.config.curse_of_elements && (spell.school == common::School::Fire || spell.school == common::School::Frost) || self.config.curse_of_shadows && (spell.school == common::School::Arcane || spell.school == common::School::Shadow) {
+        if auras.has_any(aura::CURSE_OF_ELEMENTS) && (spell.school == common::School::Fire || spell.school == common::School::Frost) {
+            dmg*= 1.1;
+        }
+        if auras.has_any(aura::CURSE_OF_SHADOWS) && (spell.school == common::School::Arcane || spell.school == common::School::Shadow) {
             dmg*= 1.1;
         }
+        if let Some(aura) = auras.get_aura(aura::SHADOW_VULNERABILITY, 0).filter(|_| spell.school == common::School::Shadow) {
+            dmg*= 1.0 + aura.value;
+        }
+        if auras.has_any(aura::STORMSTRIKE) && spell.school == common::School::Nature {
+            dmg*= 1.2;
+        }
+        if auras.has_any(aura::SPELL_VULNERABILITY) {
+            dmg*= 1.15;
+        }
 
         dmg
     }
@@ -1035,12 +1434,12 @@ impl Sim {
      * May not match exactly, but it is the best estimate we got
      * <https://royalgiraffe.github.io/legacy-sim/>
      */
-    fn spell_dmg_resist(&mut self, unit_id: i32, instance: &spell::SpellInstance) -> f64 {
+    fn spell_dmg_resist(&mut self, unit_id: i32, instance: &spell::SpellInstance, target_id: i32) -> f64 {
         if instance.spell.is_binary {
             return 0.0;
         }
 
-        let mut resist_score: f64 = self.spell_resist_score(unit_id, &instance.spell, true);
+        let mut resist_score: f64 = self.spell_resist_score(unit_id, &instance.spell, target_id, true);
 
         // Dots only use 10% of the resistance
         // But only if the dot has no initial damage (like fireball, pyroblast)
@@ -1071,7 +1470,7 @@ impl Sim {
             }
         }
 
-        let mut roll: f64 = self.rng.gen_range(0..=99) as f64;
+        let mut roll: f64 = self.rngs.get(Stream::Resist, unit_id).gen_range(0..=99) as f64;
         let mut resistance_multiplier = 0.0;
 
         for (n, percentage) in percentages.iter_mut().enumerate() {
@@ -1090,11 +1489,12 @@ impl Sim {
         }
     }
 
-    fn spell_resist_score(&self, unit_id: i32, spell: &spell::Spell, level_based: bool) -> f64 {
+    fn spell_resist_score(&self, unit_id: i32, spell: &spell::Spell, target_id: i32, level_based: bool) -> f64 {
         let mut resist_score: f64 = (self.config.target_resistance as f64) - self.unit(unit_id).spell_penetration(spell.school);
         let unit_level = self.unit(unit_id).level();
+        let auras = &self.target(target_id).auras;
 
-        if self.config.curse_of_elements && (spell.school == common::School::Fire || spell.school == common::School::Frost) {
+        if auras.has_any(aura::CURSE_OF_ELEMENTS) && (spell.school == common::School::Fire || spell.school == common::School::Frost) {
             if unit_level >= 56 {
                 resist_score-= 75.0;
             } else if unit_level >= 44 {
@@ -1104,7 +1504,7 @@ impl Sim {
             }
         }
 
-        if self.config.curse_of_elements && (spell.school == common::School::Arcane || spell.school == common::School::Shadow) {
+        if auras.has_any(aura::CURSE_OF_SHADOWS) && (spell.school == common::School::Arcane || spell.school == common::School::Shadow) {
             if unit_level >= 56 {
                 resist_score-= 75.0;
             } else if unit_level >= 44 {
@@ -1128,61 +1528,469 @@ impl Sim {
         resist_score
     }
 
-    pub fn log_push(&mut self, log: log::LogEntry) {
+    pub fn log_push(&mut self, mut log: log::LogEntry) {
         if self.log_enabled {
+            log.text = log.describe();
             self.log.push(log);
         }
     }
 
     pub fn log(&mut self, log_type: log::LogType, text: String, unit_id: i32) {
-        self.log_push(log::LogEntry {
-            log_type,
-            text,
-            unit_name: self.unit(unit_id).name(),
-            t: self.t,
-            mana: self.unit(unit_id).current_mana(),
-            mana_percent: self.unit(unit_id).mana_percent(),
-            dps: self.unit_total_dmg(unit_id) as f64 / self.t,
-            total_dps: self.total_dmg() as f64 / self.t,
-            ignite_dps: self.total_ignite_dmg() as f64 / self.t,
-            value: 0.0,
-            value2: 0.0,
-            spell_result: spell::SpellResult::None,
-        });
+        self.log_value(log_type, text, unit_id, 0.0);
     }
 
     pub fn log_value(&mut self, log_type: log::LogType, text: String, unit_id: i32, value: f64) {
-        self.log_push(log::LogEntry {
+        if !self.log_enabled {
+            return;
+        }
+
+        let mut entry = self.log_entry(log_type, unit_id, value);
+        entry.text = text;
+        self.log_push(entry);
+    }
+
+    pub fn log_entry(&self, log_type: log::LogType, unit_id: i32, value: f64) -> log::LogEntry {
+        // Unit 0 is the environment
+        let (unit_name, mana, mana_percent) = match self.units.get(&unit_id) {
+            Some(unit) => (unit.name(), unit.current_mana(), unit.mana_percent()),
+            None => (String::from("Environment"), 0.0, 0.0),
+        };
+
+        log::LogEntry {
             log_type,
-            text,
-            unit_name: self.unit(unit_id).name(),
+            unit_name,
             t: self.t,
-            mana: self.unit(unit_id).current_mana(),
-            mana_percent: self.unit(unit_id).mana_percent(),
+            mana,
+            mana_percent,
             dps: self.unit_total_dmg(unit_id) as f64 / self.t,
             total_dps: self.total_dmg() as f64 / self.t,
             ignite_dps: self.total_ignite_dmg() as f64 / self.t,
             value,
-            value2: 0.0,
-            spell_result: spell::SpellResult::None,
-        });
+            unit_id,
+            mana_before: mana,
+            mana_after: mana,
+            ..Default::default()
+        }
     }
 
-    pub fn log_spell_impact(&mut self, unit_id: i32, instance: &spell::SpellInstance, target_id: i32) {
-        self.log_push(log::LogEntry {
-            log_type: log::LogType::SpellImpact,
-            text: format!("s[{}] -> t[{}]", instance.spell.name, self.target(target_id).name),
-            unit_name: self.unit(unit_id).name(),
-            t: self.t,
-            mana: self.unit(unit_id).current_mana(),
-            mana_percent: self.unit(unit_id).mana_percent(),
-            dps: self.unit_total_dmg(unit_id) as f64 / self.t,
-            total_dps: self.total_dmg() as f64 / self.t,
-            ignite_dps: self.total_ignite_dmg() as f64 / self.t,
-            value: instance.dmg,
-            value2: instance.resist,
-            spell_result: instance.result,
-        });
+    // Entry with the spell, aura, cooldown and target of the event that caused it
+    pub fn log_event(&self, log_type: log::LogType, event: &Event, value: f64) -> log::LogEntry {
+        let mut entry = self.log_entry(log_type, event.unit_id, value);
+        entry.event_type = event.event_type;
+
+        if let Some(spell) = event.spell.as_ref() {
+            self.set_log_spell(&mut entry, spell);
+        }
+        if let Some(instance) = event.spell_instance.as_ref() {
+            self.set_log_spell(&mut entry, &instance.spell);
+            entry.spell_result = instance.result;
+            entry.resist = instance.resist;
+            entry.is_resisted = instance.is_resisted;
+        }
+        if let Some(aura) = event.aura.as_ref() {
+            entry.aura_id = aura.id;
+            entry.aura_name.clone_from(&aura.name);
+            entry.aura_school = aura.school;
+        }
+        if let Some(cooldown) = event.cooldown.as_ref() {
+            entry.cooldown_id = cooldown.id;
+            entry.cooldown_name.clone_from(&cooldown.name);
+        }
+        if let Some(target) = self.targets.get(&event.target_id) {
+            entry.target_id = event.target_id;
+            entry.target_name.clone_from(&target.name);
+        }
+
+        entry
+    }
+
+    fn set_log_spell(&self, entry: &mut log::LogEntry, spell: &spell::Spell) {
+        entry.spell_id = spell.id;
+        entry.spell_name.clone_from(&spell.name);
+        entry.school = spell.school;
+        entry.is_periodic = spell.is_dot;
+    }
+
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::common::Class;
+    use crate::config::PlayerConfig;
+    use crate::config::SupportConfig;
+    use crate::mage::TALENT_ARCANE_SUBTLETY;
+    use crate::mage::TALENT_SHATTER;
+    use crate::stats::Stats;
+    use crate::warlock::TALENT_IMP_SHADOW_BOLT;
+
+    fn test_sim(talents: Vec<u8>) -> Sim {
+        let player = PlayerConfig {
+            name: String::from("Test"),
+            level: 60,
+            talents,
+            ..Default::default()
+        };
+        let config = Config {
+            duration: 120.0,
+            target_level: 63,
+            target_resistance: 50,
+            targets: 1,
+            players: vec![player],
+            ..Default::default()
+        };
+
+        let mut sim = Sim::new(config);
+        sim.reset();
+        sim
+    }
+
+    // A mage that casts fireball on the first target until it runs out of mana
+    pub(crate) fn fireball_config(duration: f64) -> Config {
+        let mut config = test_sim(vec![0; 49]).config;
+        config.duration = duration;
+        let mut item = crate::apl::AplItem::default();
+        item.action.key = crate::apl::AplActionKey::Fireball;
+        item.action.target_id = 1;
+        config.players[0].apl.items.push(item);
+        config.players[0].stats.int = 300.0;
+        config
+    }
+
+    #[test]
+    fn arcane_subtlety() {
+        let mut talents = vec![0; 49];
+        let sim = test_sim(talents.clone());
+        assert_eq!(sim.spell_resist_score(1, &spell::fireball(), 1, false), 50.0);
+
+        talents[TALENT_ARCANE_SUBTLETY] = 2;
+        let sim = test_sim(talents);
+        assert_eq!(sim.spell_resist_score(1, &spell::fireball(), 1, false), 40.0);
+        assert_eq!(sim.spell_resist_score(1, &spell::arcane_missiles(), 1, false), 40.0);
+    }
+
+    #[test]
+    fn shatter() {
+        let mut talents = vec![0; 49];
+        talents[TALENT_SHATTER] = 3;
+        let mut sim = test_sim(talents);
+
+        let crit = sim.spell_crit_chance(1, &spell::frostbolt(), 1);
+        sim.targets.get_mut(&1).unwrap().auras.add(aura::frost_nova());
+        assert_eq!(sim.spell_crit_chance(1, &spell::frostbolt(), 1) - crit, 30.0);
+
+        let mut sim = test_sim(vec![0; 49]);
+        let crit = sim.spell_crit_chance(1, &spell::frostbolt(), 1);
+        sim.targets.get_mut(&1).unwrap().auras.add(aura::frostbite());
+        assert_eq!(sim.spell_crit_chance(1, &spell::frostbolt(), 1), crit);
+    }
+
+    #[test]
+    fn debuffs() {
+        let mut sim = test_sim(vec![0; 49]);
+        let fire = sim.spell_debuff_dmg_multiplier(1, &spell::fireball(), 1);
+        let arcane = sim.spell_resist_score(1, &spell::arcane_missiles(), 1, false);
+
+        // Curse of Shadows lowers arcane resistance, not fire
+        sim.targets.get_mut(&1).unwrap().auras.add(aura::curse_of_shadows());
+        assert_eq!(sim.spell_resist_score(1, &spell::arcane_missiles(), 1, false), (arcane - 75.0).max(0.0));
+        assert_eq!(sim.spell_resist_score(1, &spell::fireball(), 1, false), 50.0);
+        assert_eq!(sim.spell_debuff_dmg_multiplier(1, &spell::fireball(), 1), fire);
+
+        sim.targets.get_mut(&1).unwrap().auras.add(aura::spell_vulnerability());
+        assert!((sim.spell_debuff_dmg_multiplier(1, &spell::fireball(), 1) - fire * 1.15).abs() < 0.000001);
+
+        // Old flags are turned into permanent debuffs
+        let mut sim = test_sim(vec![0; 49]);
+        sim.config.curse_of_elements = true;
+        sim.config.debuffs.push(debuff::DebuffConfig { debuff: debuff::Debuff::Stormstrike, uptime: 50.0, t_apply: 10.0, ..Default::default() });
+        sim.config.targets = 2;
+        sim.reset();
+        sim.push_debuffs();
+        assert_eq!(sim.queue.len(), 2 + 2 * 10);
+        sim.work();
+        assert!(sim.target(2).auras.has_any(aura::CURSE_OF_ELEMENTS));
+        assert!(sim.target(2).auras.has_any(aura::STORMSTRIKE));
+        assert_eq!(sim.spell_resist_score(1, &spell::fireball(), 2, false), 0.0);
+    }
+
+    #[test]
+    fn invalid_config() {
+        let mut config = test_sim(vec![0; 49]).config;
+        config.players[0].class = Class::Shaman;
+
+        // Refused before any unit is spawned
+        assert_eq!(run_single(config.clone()).err().unwrap(), "Player 1: Shaman can only be used as a support");
+        assert!(run_multiple(config, 2).is_err());
+    }
+
+    #[test]
+    fn shared_debuff_refresh() {
+        let mut config = test_sim(vec![0; 49]).config;
+        config.duration = 450.0;
+        config.duration_variance = 0.0;
+        config.curse_of_elements = false;
+        let lock = PlayerConfig { class: Class::Warlock, level: 60, talents: vec![0; 50], curse: crate::warlock::Curse::Elements, ..Default::default() };
+        config.players = vec![lock.clone(), lock];
+
+        let mut sim = Sim::new(config);
+        sim.reset();
+
+        // The second warlock refreshes the curse of the first one, which would have run out at 300
+        for (unit_id, t) in [(1, 0.0), (2, 200.0)] {
+            let mut event = Event::new(EventType::AuraGain);
+            event.t = t;
+            event.unit_id = unit_id;
+            event.target_id = 1;
+            event.aura = Some(aura::curse_of_elements());
+            sim.push_event(event);
+        }
+        sim.work();
+
+        assert_eq!(sim.t, 450.0);
+        assert!(sim.target(1).auras.has_any(aura::CURSE_OF_ELEMENTS));
+    }
+
+    #[test]
+    fn improved_shadow_bolt() {
+        let mut sim = test_sim(vec![0; 49]);
+        let mut aura = aura::shadow_vulnerability(3);
+        aura.owner_id = 1;
+        sim.targets.get_mut(&1).unwrap().auras.add(aura);
+        assert!((sim.spell_debuff_dmg_multiplier(1, &spell::shadow_bolt(), 1) - 1.12).abs() < 0.000001);
+
+        let impact = |spell: spell::Spell| {
+            let mut instance = spell::SpellInstance::new(spell);
+            instance.result = spell::SpellResult::Hit;
+            instance.dmg = 500.0;
+            let mut event = Event::new(EventType::SpellImpact);
+            event.unit_id = 1;
+            event.target_id = 1;
+            event.spell_instance = Some(instance);
+            event
+        };
+
+        // Dot ticks don't use up charges
+        sim.on_spell_impact(&mut impact(spell::corruption()));
+        assert_eq!(sim.target(1).auras.stacks(aura::SHADOW_VULNERABILITY, 1), 4);
+
+        for charges in (0..4).rev() {
+            sim.on_spell_impact(&mut impact(spell::shadow_bolt()));
+            assert_eq!(sim.target(1).auras.stacks(aura::SHADOW_VULNERABILITY, 1), charges);
+        }
+        assert!(!sim.target(1).auras.has_any(aura::SHADOW_VULNERABILITY));
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn debuff_applications() {
+        let mut rng = new_rng(1);
+
+        let config = debuff::DebuffConfig { debuff: debuff::Debuff::Stormstrike, uptime: 25.0, t_apply: 6.0, ..Default::default() };
+        let apps = debuff::applications(&config, 60.0, &mut rng);
+        assert_eq!(apps, vec![(6.0, 3.0), (18.0, 3.0), (30.0, 3.0), (42.0, 3.0), (54.0, 3.0)]);
+
+        let config = debuff::DebuffConfig { debuff: debuff::Debuff::Nightfall, proc_rate: 2.0, ..Default::default() };
+        let apps = debuff::applications(&config, 3000.0, &mut rng);
+        assert!(apps.len() > 70 && apps.len() < 130);
+        assert!(apps.iter().all(|(t, duration)| *t <= 3000.0 && *duration == 5.0));
+    }
+
+    #[test]
+    fn supports() {
+        let mut config = test_sim(vec![0; 49]).config;
+        config.duration = 400.0;
+        config.players.push(config.players[0].clone());
+        config.players[1].name = String::from("Test 2");
+        config.supports.push(SupportConfig { class: Class::Priest, player_ids: vec![1, 2], ..Default::default() });
+        config.supports.push(SupportConfig { class: Class::Druid, name: String::from("Druid"), player_ids: vec![2], schedule: vec![30.0, 60.0] });
+        config.supports.push(SupportConfig { class: Class::Shaman, player_ids: vec![1, 2, 3], schedule: vec![50.0], ..Default::default() });
+
+        let result = run_single(config).unwrap();
+        let gains = |name: &str| -> Vec<(f64, String)> {
+            result.log.iter()
+                .filter(|l| l.log_type == log::LogType::AuraGain && l.text == format!("a[{}]", name))
+                .map(|l| (l.t, l.unit_name.clone()))
+                .collect()
+        };
+
+        // One power infusion every 3 minutes, shared between the players
+        assert_eq!(gains("Power Infusion"), vec![(0.0, String::from("Test")), (180.0, String::from("Test 2")), (360.0, String::from("Test"))]);
+        assert_eq!(gains("Innervate"), vec![(30.0, String::from("Test 2")), (390.0, String::from("Test 2"))]);
+        assert_eq!(gains("Mana Tide"), vec![(50.0, String::from("Test")), (50.0, String::from("Test 2"))]);
+        assert!(result.players.iter().all(|p| p.mana.sources.contains_key("Mana Tide")));
+        assert!(result.log.iter().any(|l| l.unit_name == "Priest" && l.log_type == log::LogType::CastSuccess));
+        assert_eq!(result.players.len(), 2);
+    }
+
+    #[test]
+    fn log_entries() {
+        let config = fireball_config(60.0);
+        let result = run_single(config).unwrap();
+
+        let impacts: Vec<&log::LogEntry> = result.log.iter().filter(|l| l.log_type == log::LogType::SpellImpact).collect();
+        assert!(!impacts.is_empty());
+        for l in impacts {
+            assert_eq!(l.event_type, EventType::SpellImpact);
+            assert!(l.spell_id != 0 && l.target_id == 1);
+            assert_eq!(l.text, format!("s[{}] -> t[{}]", l.spell_name, l.target_name));
+        }
+
+        for l in result.log.iter() {
+            match l.log_type {
+                log::LogType::CastSuccess => assert!(l.mana_before >= l.mana_after),
+                log::LogType::Mana => assert!(l.mana_after - l.mana_before <= l.value),
+                log::LogType::AuraGain | log::LogType::AuraExpire => assert!(l.aura_id != 0),
+                log::LogType::CooldownGain | log::LogType::CooldownExpire => assert_eq!(l.text, format!("c[{}]", l.cooldown_name)),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn timelines() {
+        let mut config = fireball_config(60.0);
+        config.timeline_bucket = 10.0;
+        config.rng_seed = 1;
+        config.players[0].stats.int = 200.0;
+
+        let result = run_multiple(config.clone(), 20).unwrap();
+        let timeline = &result.timelines[0];
+        assert_eq!(result.timelines.len(), 1);
+        assert_eq!(timeline.dmg.len(), 6);
+        assert_eq!(timeline.mana_percent[0], 100.0);
+        assert!(timeline.mana[5] < timeline.mana[0]);
+        assert!((timeline.dmg.iter().sum::<f64>() / 60.0 - result.players[0].dps).abs() < 0.001);
+
+        // The player runs out of mana before the end
+        assert_eq!(timeline.oom[0], 0.0);
+        assert_eq!(timeline.oom[5], 1.0);
+
+        config.timeline_bucket = 0.0;
+        assert!(run_multiple(config, 2).unwrap().timelines.is_empty());
+    }
+
+    #[test]
+    fn mana_results() {
+        let mut config = fireball_config(60.0);
+        config.rng_seed = 1;
+        config.judgement_of_wisdom = true;
+        config.players[0].stats.int = 200.0;
+        config.players[0].stats.spi = 100.0;
+
+        let mut sim = Sim::new(config.clone());
+        let result = sim.run();
+        let mana = &result.players[0].mana;
+        let unit = sim.unit(1);
+
+        assert!(mana.t_oom.unwrap() > 0.0 && mana.t_oom.unwrap() < 60.0);
+        assert!(mana.t_waiting > 0.0);
+        assert_eq!(mana.spent, mana.spells.values().sum::<f64>());
+        assert!(mana.spells["Fireball"] > 0.0);
+        assert!(mana.sources["Regen"] > 0.0 && mana.sources["Judgement of Wisdom"] > 0.0);
+        assert!((unit.max_mana() + mana.sources.values().sum::<f64>() - mana.spent - unit.current_mana()).abs() < 0.001);
+        assert_eq!(mana.dmg_per_mana, result.players[0].dmg as f64 / mana.spent);
+
+        let result = run_multiple(config, 10).unwrap();
+        assert_eq!(result.players[0].mana.oom_rate, 1.0);
+        assert_eq!(result.players[0].mana.oom_runs, 10);
+        assert!(result.players[0].mana.t_oom.is_some());
+
+        // The time to oom is the mean of the runs that went oom
+        let run = |t_oom: Option<f64>| ManaResult { t_oom, oom_runs: t_oom.is_some() as u32, oom_rate: t_oom.is_some() as u8 as f64, ..Default::default() };
+        let mut mean = run(Some(10.0));
+        mean.add_run(&run(None), 2.0);
+        mean.add_run(&run(Some(30.0)), 3.0);
+        assert_eq!((mean.t_oom, mean.oom_runs), (Some(20.0), 2));
+        assert!((mean.oom_rate - 2.0 / 3.0).abs() < 0.000001);
+    }
+
+    #[test]
+    fn rng_streams() {
+        let mut config = fireball_config(60.0);
+        config.rng_seed = 1;
+        config.players.push(config.players[0].clone());
+
+        // Identical players roll their own numbers
+        let result = Sim::new(config.clone()).run();
+        assert_ne!(result.players[0].dmg, result.players[1].dmg);
+
+        // Changing one player doesn't change the rolls of the other
+        config.players[1].stats.sp = 200.0;
+        config.players[1].stats.crit = 10.0;
+        let changed = Sim::new(config).run();
+        assert_eq!(changed.players[0].dmg, result.players[0].dmg);
+        assert!(changed.players[1].dmg > result.players[1].dmg);
+    }
+
+    #[test]
+    fn iteration_results() {
+        let mut config = test_sim(vec![0; 49]).config;
+        config.rng_seed = 100;
+        assert!(run_multiple(config.clone(), 3).unwrap().iteration_results.is_empty());
+
+        config.keep_iterations = true;
+        let result = run_multiple(config, 3).unwrap();
+        let seeds: Vec<u64> = result.iteration_results.iter().map(|r| r.seed).collect();
+        assert_eq!(seeds, vec![100, 101, 102]);
+        assert!(result.iteration_results.iter().all(|r| r.player_dps.len() == 1));
+    }
+
+    #[test]
+    fn replays() {
+        let mut config = fireball_config(120.0);
+        config.duration_variance = 10.0;
+
+        let result = run_multiple(config.clone(), 15).unwrap();
+        assert!(result.rng_seed != 0);
+        assert_eq!(result.min_iteration.dps, result.min_dps);
+        assert_eq!(result.max_iteration.dps, result.max_dps);
+        assert!(result.median_iteration.dps >= result.min_dps && result.median_iteration.dps <= result.max_dps);
+
+        config.rng_seed = result.rng_seed;
+        for r in [&result.min_iteration, &result.median_iteration, &result.max_iteration] {
+            let run = replay(config.clone(), r.iteration).unwrap();
+            assert_eq!(run.dps, r.dps);
+            assert_eq!(run.seed, r.seed);
+        }
+
+        let a = replay(config.clone(), 7).unwrap();
+        let b = replay(config, 7).unwrap();
+        assert!(!a.log.is_empty());
+        assert!(a.log.iter().map(|l| (l.t, l.text.clone(), l.value)).eq(b.log.iter().map(|l| (l.t, l.text.clone(), l.value))));
+    }
+
+    #[test]
+    fn warlocks() {
+        let mut config = test_sim(vec![0; 49]).config;
+        config.rng_seed = 1;
+        config.curse_of_elements = false;
+        let mut talents = vec![0; 50];
+        talents[TALENT_IMP_SHADOW_BOLT] = 5;
+        config.players[0] = PlayerConfig {
+            name: String::from("Lock"),
+            class: Class::Warlock,
+            level: 60,
+            talents,
+            curse: crate::warlock::Curse::Elements,
+            stats: Stats { int: 250.0, sp: 500.0, crit: 10.0, hit: 5.0, ..Default::default() },
+            ..Default::default()
+        };
+        config.players.push(config.players[0].clone());
+        config.players[1].name = String::from("Lock 2");
+        config.players[1].curse = crate::warlock::Curse::Shadows;
+
+        let result = run_single(config).unwrap();
+        let casts = |unit: &str, text: &str| result.log.iter().filter(|l| l.log_type == log::LogType::CastSuccess && l.unit_name == unit && l.text == text).count();
+
+        // Each warlock keeps up its own curse, the target gets both
+        assert_eq!(casts("Lock", "s[Curse of the Elements]"), 1);
+        assert_eq!(casts("Lock", "s[Curse of Shadow]"), 0);
+        assert_eq!(casts("Lock 2", "s[Curse of Shadow]"), 1);
+        assert!(casts("Lock", "s[Corruption]") >= 2);
+        assert!(casts("Lock", "s[Shadow Bolt]") > 10);
+        assert!(casts("Lock", "s[Life Tap]") > 0);
+        assert!(result.log.iter().any(|l| l.log_type == log::LogType::AuraGain && l.text == "a[Shadow Vulnerability] (4) -> t[Target 1]"));
+        assert!(result.players.iter().all(|p| p.dps > 0.0));
+        assert!(result.players.iter().all(|p| p.class == Class::Warlock));
+    }
+}
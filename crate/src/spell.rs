@@ -1,17 +1,25 @@
 use crate::common::School;
 use serde::{Serialize, Deserialize};
 
+pub const ARCANE_EXPLOSION: i32 = 10202;
 pub const ARCANE_MISSILES: i32 = 4145;
 pub const ARCANE_POWER: i32 = 12042;
 pub const BERSERKING: i32 = 20554;
-pub const COLD_SNAP: i32 = 11958;
+pub const BLAST_WAVE: i32 = 13021;
+pub const COLD_SNAP: i32 = 12472;
 pub const COMBUSTION: i32 = 29977;
+pub const CONE_OF_COLD: i32 = 10161;
 pub const EVOCATION: i32 = 12051;
 pub const FIREBALL: i32 = 8400;
 pub const FIREBALL_DOT: i32 = -8400; // fake id
 pub const FIRE_BLAST: i32 = 10199;
 pub const FIRE_VULNERABILITY: i32 = 22959;
+pub const FLAMESTRIKE: i32 = 10216;
+pub const FLAMESTRIKE_DOT: i32 = -10216; // fake id
 pub const FROSTBOLT: i32 = 7322;
+pub const FROST_NOVA: i32 = 10230;
+pub const ICE_BARRIER: i32 = 13033;
+pub const ICE_BLOCK: i32 = 11958;
 pub const IGNITE: i32 = 12848;
 pub const INNERVATE: i32 = 29166;
 pub const MANA_TIDE: i32 = 17359;
@@ -38,6 +46,13 @@ pub const ENGULFING_SHADOWS: i32 = 27860;
 pub const MANA_GEM: i32 = 10058;
 pub const MANA_POTION: i32 = 17531;
 pub const ROBE_ARCHMAGE: i32 = 18385;
+// Warlock
+pub const CORRUPTION: i32 = 25311;
+pub const CURSE_OF_AGONY: i32 = 11713;
+pub const CURSE_OF_ELEMENTS: i32 = 11722;
+pub const CURSE_OF_SHADOWS: i32 = 17937;
+pub const LIFE_TAP: i32 = 11689;
+pub const SHADOW_BOLT: i32 = 25307;
 
 #[derive(Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SpellResult {
@@ -62,7 +77,9 @@ pub struct Spell {
     pub cooldown: f64,
     pub gcd: f64,
     pub speed: f64,
+    pub range: f64,
     pub rank: i32,
+    pub level: i32,
     pub is_channeled: bool,
     pub is_dot: bool,
     pub is_proc: bool,
@@ -96,7 +113,9 @@ impl Spell {
             cooldown: 0.0,
             gcd: 1.5,
             speed: 0.0,
+            range: 0.0,
             rank: 1,
+            level: 60,
             is_channeled: false,
             is_dot: false,
             is_proc: false,
@@ -122,12 +141,27 @@ impl Spell {
             return 0.0;
         }
 
+        // Can't cast from further away than the range of the spell
+        let mut distance = distance;
+        if self.range > 0.0 {
+            distance = distance.min(self.range);
+        }
+
         (distance / self.speed).max(0.0)
     }
 
     pub fn is_hidden(&self) -> bool {
         self.gcd == 0.0
     }
+
+    // Spells learned before level 20 have a reduced coefficient
+    pub fn level_penalty(&self) -> f64 {
+        if self.level < 20 {
+            return 1.0 - (20 - self.level) as f64 * 0.0375;
+        }
+
+        1.0
+    }
 }
 
 #[derive(Clone)]
@@ -136,6 +170,8 @@ pub struct SpellInstance {
     pub result: SpellResult,
     pub dmg: f64,
     pub resist: f64,
+    // A miss caused by the target's resistance
+    pub is_resisted: bool,
     pub tick: u8,
 }
 
@@ -146,6 +182,7 @@ impl SpellInstance {
             result: SpellResult::None,
             dmg: 0.0,
             resist: 0.0,
+            is_resisted: false,
             tick: 0,
         }
     }
@@ -156,38 +193,49 @@ impl SpellInstance {
  * Defined spells
  */
 
+// Arcane Explosion
+pub fn arcane_explosion() -> Spell {
+    let mut spell = Spell::new(ARCANE_EXPLOSION, String::from("Arcane Explosion"), School::Arcane);
+    spell.rank = 6;
+    spell.min_dmg = 250.0;
+    spell.max_dmg = 271.0;
+    spell.mana_cost = 390.0;
+    spell.coeff = 0.143;
+    spell.is_aoe = true;
+    spell
+}
+
 // Arcane Missiles
 pub fn arcane_missiles() -> Spell {
     arcane_missiles_ranked(8)
 }
 pub fn arcane_missiles_ranked(rank: i32) -> Spell {
     let mut spell = Spell::new(ARCANE_MISSILES, String::from("Arcane Missiles"), School::Arcane);
-    spell.rank = rank;
     spell.coeff = 0.24;
-    spell.cast_time = 5.0;
     spell.is_channeled = true;
     spell.is_dynamic = true;
-    spell.ticks = 5;
     spell.speed = 20.0;
-
-    match rank {
-        8 => {
-            spell.min_dmg = 196.0;
-            spell.max_dmg = 196.0;
-            spell.mana_cost = 595.0;
-        }
-        7 => {
-            spell.min_dmg = 155.0;
-            spell.max_dmg = 155.0;
-            spell.mana_cost = 500.0;
-        }
-        _ => {
-            spell.min_dmg = 26.0;
-            spell.max_dmg = 26.0;
-            spell.mana_cost = 85.0;
-            spell.coeff = 0.132;
-        }
-    }
+    spell.range = 30.0;
+
+    // Damage per missile
+    let (rank, level, dmg, mana_cost, ticks) = match rank {
+        1 => (1, 8, 26.0, 85.0, 3),
+        2 => (2, 16, 38.0, 140.0, 4),
+        3 => (3, 24, 58.0, 235.0, 5),
+        4 => (4, 32, 86.0, 320.0, 5),
+        5 => (5, 40, 118.0, 410.0, 5),
+        6 => (6, 48, 136.0, 455.0, 5),
+        7 => (7, 56, 155.0, 500.0, 5),
+        _ => (8, 60, 196.0, 595.0, 5),
+    };
+    spell.rank = rank;
+    spell.level = level;
+    spell.min_dmg = dmg;
+    spell.max_dmg = dmg;
+    spell.mana_cost = mana_cost;
+    spell.ticks = ticks;
+    spell.cast_time = ticks as f64;
+    spell.coeff*= spell.level_penalty();
     spell
 }
 
@@ -218,6 +266,19 @@ pub fn berserking() -> Spell {
     spell
 }
 
+// Blast Wave
+pub fn blast_wave() -> Spell {
+    let mut spell = Spell::new(BLAST_WAVE, String::from("Blast Wave"), School::Fire);
+    spell.rank = 5;
+    spell.min_dmg = 462.0;
+    spell.max_dmg = 544.0;
+    spell.mana_cost = 545.0;
+    spell.coeff = 0.129;
+    spell.cooldown = 45.0;
+    spell.is_aoe = true;
+    spell
+}
+
 // Burst of Knowledge
 pub fn burst_of_knowledge() -> Spell {
     let mut spell = Spell::new(BURST_OF_KNOWLEDGE, String::from("Burst of Knowledge"), School::Arcane);
@@ -272,6 +333,20 @@ pub fn combustion() -> Spell {
     spell
 }
 
+// Cone of Cold
+pub fn cone_of_cold() -> Spell {
+    let mut spell = Spell::new(CONE_OF_COLD, String::from("Cone of Cold"), School::Frost);
+    spell.rank = 5;
+    spell.min_dmg = 335.0;
+    spell.max_dmg = 365.0;
+    spell.mana_cost = 210.0;
+    spell.coeff = 0.129;
+    spell.cooldown = 10.0;
+    spell.is_aoe = true;
+    spell.is_binary = true;
+    spell
+}
+
 // Ephemeral Power - Talisman of Ephemeral Power
 pub fn ephemeral_power() -> Spell {
     let mut spell = Spell::new(EPHEMERAL_POWER, String::from("Ephemeral Power"), School::Arcane);
@@ -307,30 +382,30 @@ pub fn fireball() -> Spell {
 }
 pub fn fireball_ranked(rank: i32) -> Spell {
     let mut spell = Spell::new(FIREBALL, String::from("Fireball"), School::Fire);
-    spell.rank = rank;
-    spell.coeff = 1.0;
-    spell.cast_time = 3.5;
     spell.speed = 24.0;
-
-    match rank {
-        12 => {
-            spell.min_dmg = 596.0;
-            spell.max_dmg = 760.0;
-            spell.mana_cost = 410.0;
-        }
-        11 => {
-            spell.min_dmg = 561.0;
-            spell.max_dmg = 715.0;
-            spell.mana_cost = 395.0;
-        }
-        _ => {
-            spell.min_dmg = 16.0;
-            spell.max_dmg = 25.0;
-            spell.mana_cost = 30.0;
-            spell.coeff = 0.123;
-            spell.cast_time = 1.5;
-        }
-    }
+    spell.range = 35.0;
+
+    let (rank, level, min_dmg, max_dmg, mana_cost, cast_time) = match rank {
+        1 => (1, 1, 16.0, 25.0, 30.0, 1.5),
+        2 => (2, 6, 34.0, 49.0, 45.0, 2.0),
+        3 => (3, 12, 57.0, 77.0, 65.0, 2.5),
+        4 => (4, 18, 89.0, 122.0, 95.0, 3.0),
+        5 => (5, 24, 146.0, 195.0, 140.0, 3.5),
+        6 => (6, 30, 207.0, 274.0, 185.0, 3.5),
+        7 => (7, 36, 264.0, 345.0, 220.0, 3.5),
+        8 => (8, 42, 328.0, 425.0, 260.0, 3.5),
+        9 => (9, 48, 404.0, 518.0, 305.0, 3.5),
+        10 => (10, 54, 488.0, 623.0, 350.0, 3.5),
+        11 => (11, 60, 561.0, 715.0, 395.0, 3.5),
+        _ => (12, 60, 596.0, 760.0, 410.0, 3.5),
+    };
+    spell.rank = rank;
+    spell.level = level;
+    spell.min_dmg = min_dmg;
+    spell.max_dmg = max_dmg;
+    spell.mana_cost = mana_cost;
+    spell.cast_time = cast_time;
+    spell.coeff = cast_time / 3.5 * spell.level_penalty();
     spell
 }
 pub fn fireball_dot(rank: i32) -> Spell {
@@ -341,23 +416,25 @@ pub fn fireball_dot(rank: i32) -> Spell {
     spell.can_crit = false;
     spell.coeff = 0.0;
     spell.t_interval = 2.0;
-    spell.ticks = 4;
-
-    match rank {
-        12 => {
-            spell.min_dmg = 19.0;
-            spell.max_dmg = 19.0;
-        }
-        11 => {
-            spell.min_dmg = 18.0;
-            spell.max_dmg = 18.0;
-        }
-        _ => {
-            spell.min_dmg = 2.0;
-            spell.max_dmg = 2.0;
-        }
-    }
 
+    // Damage per tick
+    let (dmg, ticks) = match rank {
+        1 => (1.0, 2),
+        2 => (1.0, 3),
+        3 => (2.0, 3),
+        4 => (3.0, 4),
+        5 => (5.0, 4),
+        6 => (7.0, 4),
+        7 => (8.0, 4),
+        8 => (10.0, 4),
+        9 => (13.0, 4),
+        10 => (15.0, 4),
+        11 => (18.0, 4),
+        _ => (19.0, 4),
+    };
+    spell.min_dmg = dmg;
+    spell.max_dmg = dmg;
+    spell.ticks = ticks;
     spell
 }
 
@@ -367,24 +444,24 @@ pub fn fire_blast() -> Spell {
 }
 pub fn fire_blast_ranked(rank: i32) -> Spell {
     let mut spell = Spell::new(FIRE_BLAST, String::from("Fire Blast"), School::Fire);
-    spell.rank = rank;
-    spell.coeff = 0.429;
     spell.cooldown = 8.0;
-
-    match rank {
-        7 => {
-            spell.min_dmg = 446.0;
-            spell.max_dmg = 524.0;
-            spell.mana_cost = 340.0;
-        }
-        _ => {
-            spell.min_dmg = 27.0;
-            spell.max_dmg = 35.0;
-            spell.mana_cost = 40.0;
-            spell.coeff = 0.204;
-        }
-    }
-
+    spell.range = 20.0;
+
+    let (rank, level, min_dmg, max_dmg, mana_cost) = match rank {
+        1 => (1, 6, 27.0, 35.0, 40.0),
+        2 => (2, 14, 62.0, 76.0, 75.0),
+        3 => (3, 22, 110.0, 134.0, 115.0),
+        4 => (4, 30, 160.0, 192.0, 165.0),
+        5 => (5, 38, 221.0, 261.0, 220.0),
+        6 => (6, 46, 339.0, 401.0, 280.0),
+        _ => (7, 54, 446.0, 524.0, 340.0),
+    };
+    spell.rank = rank;
+    spell.level = level;
+    spell.min_dmg = min_dmg;
+    spell.max_dmg = max_dmg;
+    spell.mana_cost = mana_cost;
+    spell.coeff = 0.429 * spell.level_penalty();
     spell
 }
 
@@ -403,32 +480,90 @@ pub fn frostbolt() -> Spell {
 }
 pub fn frostbolt_ranked(rank: i32) -> Spell {
     let mut spell = Spell::new(FROSTBOLT, String::from("Frostbolt"), School::Frost);
-    spell.rank = rank;
     spell.is_binary = true;
-    spell.coeff = 0.814; // 3.0 / 3.5 * 0.95
-    spell.cast_time = 3.0;
     spell.speed = 28.0;
+    spell.range = 30.0;
+
+    let (rank, level, min_dmg, max_dmg, mana_cost, cast_time) = match rank {
+        1 => (1, 4, 18.0, 20.0, 25.0, 1.5),
+        2 => (2, 8, 31.0, 35.0, 35.0, 1.8),
+        3 => (3, 14, 51.0, 57.0, 50.0, 2.2),
+        4 => (4, 20, 74.0, 82.0, 65.0, 2.6),
+        5 => (5, 26, 126.0, 138.0, 100.0, 3.0),
+        6 => (6, 32, 174.0, 190.0, 130.0, 3.0),
+        7 => (7, 38, 227.0, 247.0, 160.0, 3.0),
+        8 => (8, 44, 292.0, 316.0, 195.0, 3.0),
+        9 => (9, 50, 353.0, 383.0, 225.0, 3.0),
+        10 => (10, 56, 440.0, 475.0, 260.0, 3.0),
+        _ => (11, 60, 515.0, 555.0, 290.0, 3.0),
+    };
+    spell.rank = rank;
+    spell.level = level;
+    spell.min_dmg = min_dmg;
+    spell.max_dmg = max_dmg;
+    spell.mana_cost = mana_cost;
+    spell.cast_time = cast_time;
+    // Slowing effect reduces the coefficient by 5%
+    spell.coeff = cast_time / 3.5 * 0.95 * spell.level_penalty();
+    spell
+}
+
+// Flamestrike
+pub fn flamestrike() -> Spell {
+    let mut spell = Spell::new(FLAMESTRIKE, String::from("Flamestrike"), School::Fire);
+    spell.rank = 6;
+    spell.min_dmg = 381.0;
+    spell.max_dmg = 467.0;
+    spell.mana_cost = 990.0;
+    spell.coeff = 0.176;
+    spell.cast_time = 3.0;
+    spell.range = 30.0;
+    spell.is_aoe = true;
+    spell
+}
+pub fn flamestrike_dot() -> Spell {
+    let mut spell = Spell::new(FLAMESTRIKE_DOT, String::from("Flamestrike (dot)"), School::Fire);
+    spell.is_dot = true;
+    spell.can_proc = false;
+    spell.can_miss = false;
+    spell.can_crit = false;
+    spell.min_dmg = 85.0;
+    spell.max_dmg = 85.0;
+    spell.coeff = 0.03;
+    spell.t_interval = 2.0;
+    spell.ticks = 4;
+    spell
+}
 
-    match rank {
-        11 => {
-            spell.mana_cost = 290.0;
-            spell.min_dmg = 515.0;
-            spell.max_dmg = 555.0;
-        }
-        10 => {
-            spell.mana_cost = 260.0;
-            spell.min_dmg = 440.0;
-            spell.max_dmg = 475.0;
-        }
-        _ => {
-            spell.mana_cost = 25.0;
-            spell.min_dmg = 18.0;
-            spell.max_dmg = 20.0;
-            spell.cast_time = 1.5;
-            spell.coeff = 0.163;
-        }
-    }
+// Frost Nova
+pub fn frost_nova() -> Spell {
+    let mut spell = Spell::new(FROST_NOVA, String::from("Frost Nova"), School::Frost);
+    spell.rank = 4;
+    spell.min_dmg = 71.0;
+    spell.max_dmg = 80.0;
+    spell.mana_cost = 115.0;
+    spell.coeff = 0.043;
+    spell.cooldown = 25.0;
+    spell.is_aoe = true;
+    spell.is_binary = true;
+    spell
+}
 
+// Ice Barrier
+pub fn ice_barrier() -> Spell {
+    let mut spell = Spell::new(ICE_BARRIER, String::from("Ice Barrier"), School::Frost);
+    spell.rank = 4;
+    spell.mana_cost = 305.0;
+    spell.cooldown = 30.0;
+    spell.is_trigger = true;
+    spell
+}
+
+// Ice Block
+pub fn ice_block() -> Spell {
+    let mut spell = Spell::new(ICE_BLOCK, String::from("Ice Block"), School::Frost);
+    spell.cooldown = 300.0;
+    spell.is_trigger = true;
     spell
 }
 
@@ -539,23 +674,26 @@ pub fn pyroblast() -> Spell {
 }
 pub fn pyroblast_ranked(rank: i32) -> Spell {
     let mut spell = Spell::new(PYROBLAST, String::from("Pyroblast"), School::Fire);
-    spell.rank = rank;
     spell.coeff = 1.0;
     spell.cast_time = 6.0;
     spell.speed = 24.0;
-
-    match rank {
-        8 => {
-            spell.min_dmg = 716.0;
-            spell.max_dmg = 890.0;
-            spell.mana_cost = 440.0;
-        }
-        _ => {
-            spell.min_dmg = 148.0;
-            spell.max_dmg = 195.0;
-            spell.mana_cost = 125.0;
-        }
-    }
+    spell.range = 35.0;
+
+    let (rank, level, min_dmg, max_dmg, mana_cost) = match rank {
+        1 => (1, 20, 148.0, 195.0, 125.0),
+        2 => (2, 24, 193.0, 250.0, 150.0),
+        3 => (3, 30, 270.0, 343.0, 195.0),
+        4 => (4, 36, 347.0, 437.0, 240.0),
+        5 => (5, 42, 427.0, 536.0, 285.0),
+        6 => (6, 48, 525.0, 654.0, 335.0),
+        7 => (7, 54, 625.0, 776.0, 385.0),
+        _ => (8, 60, 716.0, 890.0, 440.0),
+    };
+    spell.rank = rank;
+    spell.level = level;
+    spell.min_dmg = min_dmg;
+    spell.max_dmg = max_dmg;
+    spell.mana_cost = mana_cost;
     spell
 }
 pub fn pyroblast_dot(rank: i32) -> Spell {
@@ -568,17 +706,19 @@ pub fn pyroblast_dot(rank: i32) -> Spell {
     spell.t_interval = 3.0;
     spell.ticks = 4;
 
-    match rank {
-        8 => {
-            spell.min_dmg = 67.0;
-            spell.max_dmg = 67.0;
-        }
-        _ => {
-            spell.min_dmg = 14.0;
-            spell.max_dmg = 14.0;
-        }
-    }
-
+    // Damage per tick
+    let dmg = match rank {
+        1 => 14.0,
+        2 => 18.0,
+        3 => 24.0,
+        4 => 31.0,
+        5 => 39.0,
+        6 => 47.0,
+        7 => 57.0,
+        _ => 67.0,
+    };
+    spell.min_dmg = dmg;
+    spell.max_dmg = dmg;
     spell
 }
 
@@ -597,23 +737,24 @@ pub fn scorch() -> Spell {
 }
 pub fn scorch_ranked(rank: i32) -> Spell {
     let mut spell = Spell::new(SCORCH, String::from("Scorch"), School::Fire);
-    spell.rank = rank;
     spell.coeff = 0.429;
     spell.cast_time = 1.5;
-
-    match rank {
-        7 => {
-            spell.min_dmg = 237.0;
-            spell.max_dmg = 280.0;
-            spell.mana_cost = 150.0;
-        }
-        _ => {
-            spell.min_dmg = 56.0;
-            spell.max_dmg = 69.0;
-            spell.mana_cost = 50.0;
-        }
-    }
-
+    spell.range = 30.0;
+
+    let (rank, level, min_dmg, max_dmg, mana_cost) = match rank {
+        1 => (1, 22, 56.0, 69.0, 50.0),
+        2 => (2, 28, 81.0, 98.0, 65.0),
+        3 => (3, 34, 105.0, 126.0, 80.0),
+        4 => (4, 40, 139.0, 165.0, 100.0),
+        5 => (5, 46, 168.0, 199.0, 115.0),
+        6 => (6, 52, 207.0, 247.0, 135.0),
+        _ => (7, 58, 237.0, 280.0, 150.0),
+    };
+    spell.rank = rank;
+    spell.level = level;
+    spell.min_dmg = min_dmg;
+    spell.max_dmg = max_dmg;
+    spell.mana_cost = mana_cost;
     spell
 }
 
@@ -624,4 +765,80 @@ pub fn unstable_power() -> Spell {
     spell.is_trigger = true;
     spell.gcd = 0.0;
     spell
-}
\ No newline at end of file
+}
+
+
+/*
+ * Warlock spells
+ */
+
+// Corruption
+pub fn corruption() -> Spell {
+    let mut spell = Spell::new(CORRUPTION, String::from("Corruption"), School::Shadow);
+    spell.is_dot = true;
+    spell.can_miss = false;
+    spell.can_crit = false;
+    spell.cast_time = 2.0;
+    spell.mana_cost = 340.0;
+    spell.range = 30.0;
+    spell.t_interval = 3.0;
+    spell.ticks = 6;
+    spell.coeff = 0.156;
+    spell.min_dmg = 137.0;
+    spell.max_dmg = 137.0;
+    spell
+}
+
+// Curse of Agony
+pub fn curse_of_agony() -> Spell {
+    let mut spell = Spell::new(CURSE_OF_AGONY, String::from("Curse of Agony"), School::Shadow);
+    spell.is_dot = true;
+    spell.can_miss = false;
+    spell.can_crit = false;
+    spell.mana_cost = 265.0;
+    spell.range = 30.0;
+    spell.t_interval = 2.0;
+    spell.ticks = 12;
+    spell.coeff = 0.1;
+    spell.min_dmg = 87.0;
+    spell.max_dmg = 87.0;
+    spell
+}
+
+// Curse of the Elements
+pub fn curse_of_elements() -> Spell {
+    let mut spell = Spell::new(CURSE_OF_ELEMENTS, String::from("Curse of the Elements"), School::Shadow);
+    spell.is_trigger = true;
+    spell.mana_cost = 260.0;
+    spell.range = 30.0;
+    spell
+}
+
+// Curse of Shadow
+pub fn curse_of_shadows() -> Spell {
+    let mut spell = Spell::new(CURSE_OF_SHADOWS, String::from("Curse of Shadow"), School::Shadow);
+    spell.is_trigger = true;
+    spell.mana_cost = 260.0;
+    spell.range = 30.0;
+    spell
+}
+
+// Life Tap - 424 health to mana
+pub fn life_tap() -> Spell {
+    let mut spell = Spell::new(LIFE_TAP, String::from("Life Tap"), School::Shadow);
+    spell.is_trigger = true;
+    spell
+}
+
+// Shadow Bolt
+pub fn shadow_bolt() -> Spell {
+    let mut spell = Spell::new(SHADOW_BOLT, String::from("Shadow Bolt"), School::Shadow);
+    spell.cast_time = 3.0;
+    spell.mana_cost = 380.0;
+    spell.coeff = 0.857;
+    spell.speed = 20.0;
+    spell.range = 30.0;
+    spell.min_dmg = 482.0;
+    spell.max_dmg = 538.0;
+    spell
+}
@@ -17,7 +17,45 @@ pub struct Stats {
     pub mana: f64,
 }
 
+// The fields of Stats, named like the fields in json
+#[derive(Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Stat {
+    #[default]
+    Int,
+    Spi,
+    Mp5,
+    Crit,
+    Hit,
+    Sp,
+    SpArcane,
+    SpFire,
+    SpFrost,
+    SpNature,
+    SpShadow,
+    SpellPenetration,
+    Mana,
+}
+
 impl Stats {
+    pub fn stat_mut(&mut self, stat: Stat) -> &mut f64 {
+        match stat {
+            Stat::Int => &mut self.int,
+            Stat::Spi => &mut self.spi,
+            Stat::Mp5 => &mut self.mp5,
+            Stat::Crit => &mut self.crit,
+            Stat::Hit => &mut self.hit,
+            Stat::Sp => &mut self.sp,
+            Stat::SpArcane => &mut self.sp_arcane,
+            Stat::SpFire => &mut self.sp_fire,
+            Stat::SpFrost => &mut self.sp_frost,
+            Stat::SpNature => &mut self.sp_nature,
+            Stat::SpShadow => &mut self.sp_shadow,
+            Stat::SpellPenetration => &mut self.spell_penetration,
+            Stat::Mana => &mut self.mana,
+        }
+    }
+
     pub fn reset(&mut self) {
         self.int = 0.0;
         self.spi = 0.0;
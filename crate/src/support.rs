@@ -0,0 +1,273 @@
+use crate::aura;
+use crate::common::Class;
+use crate::common::School;
+use crate::config::Config;
+use crate::config::SupportConfig;
+use crate::cooldown;
+use crate::event::Event;
+use crate::event::EventType;
+use crate::spell;
+use crate::target::Target;
+use crate::unit::Unit;
+use std::collections::HashMap;
+
+// A priest, druid or shaman that only casts its cooldown on the players
+// Power Infusion, Innervate and Mana Tide Totem
+pub struct Support {
+    pub id: i32,
+    pub name: String,
+    pub config: Option<Config>,
+    pub auras: aura::Auras,
+    pub cooldowns: cooldown::Cooldowns,
+    index: usize,
+    t_gcd: f64,
+    schedule_index: usize,
+    player_index: usize,
+}
+
+impl Support {
+    pub fn new() -> Self {
+        Self {
+            id: 0,
+            name: String::from("Support"),
+            config: None,
+            auras: Default::default(),
+            cooldowns: Default::default(),
+            index: 0,
+            t_gcd: 0.0,
+            schedule_index: 0,
+            player_index: 0,
+        }
+    }
+
+    // Supports get ids after the players
+    pub fn set_index(&mut self, index: usize) {
+        self.index = index;
+    }
+
+    fn support_config(&self) -> &SupportConfig {
+        &self.config.as_ref().unwrap().supports[self.index]
+    }
+
+    fn support_spell(&self) -> Option<spell::Spell> {
+        let mut spell = match self.support_config().class {
+            Class::Druid => {
+                let mut spell = spell::innervate();
+                spell.cooldown = 360.0;
+                spell
+            }
+            Class::Priest => {
+                let mut spell = spell::power_infusion();
+                spell.cooldown = 180.0;
+                spell
+            }
+            Class::Shaman => {
+                let mut spell = spell::mana_tide();
+                spell.cooldown = 300.0;
+                spell
+            }
+            _ => return None,
+        };
+
+        // Casts from other units are shown in the log
+        spell.gcd = 1.5;
+
+        Some(self.this_spell(spell))
+    }
+
+    fn player_ids(&self) -> Vec<i32> {
+        let players = self.config.as_ref().unwrap().players.len() as i32;
+
+        self.support_config().player_ids.iter().copied().filter(|id| *id >= 1 && *id <= players).collect()
+    }
+
+    fn wait(&self, t: f64) -> Event {
+        let mut event = Event::new(EventType::Wait);
+        event.t = t;
+        event
+    }
+
+    fn player_aura_event(&self, mut aura: aura::Aura, player_id: i32) -> Event {
+        aura.owner_id = self.id;
+
+        Event {
+            event_type: EventType::AuraGain,
+            unit_id: player_id,
+            aura: Some(aura),
+            is_main_event: false,
+            ..Default::default()
+        }
+    }
+
+    fn player_mana_event(&self, spell: &spell::Spell, mana: f64, player_id: i32, t: f64) -> Event {
+        Event {
+            t,
+            event_type: EventType::ManaGain,
+            unit_id: player_id,
+            text: spell.name.clone(),
+            mana,
+            is_main_event: false,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for Support {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Unit for Support {
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn reset(&mut self) {
+        self.auras.reset();
+        self.cooldowns.reset();
+        self.t_gcd = 0.0;
+        self.schedule_index = 0;
+        self.player_index = 0;
+    }
+
+    fn set_config(&mut self, config: Config) {
+        self.config = Some(config);
+    }
+
+    // Supports don't roll anything
+    fn new_rng(&mut self, rng_seed: u64) {}
+
+    // Supports don't run out of mana
+    fn max_mana(&self) -> f64 {
+        0.0
+    }
+
+    fn current_mana(&self) -> f64 {
+        0.0
+    }
+
+    fn mana_percent(&self) -> f64 {
+        100.0
+    }
+
+    fn mod_mana(&mut self, mana: f64, t: f64) {}
+
+    fn mana_per_second(&self, t: f64) -> f64 {
+        0.0
+    }
+
+    fn spirit_regen(&self) -> f64 {
+        0.0
+    }
+
+    fn mp5(&self) -> f64 {
+        0.0
+    }
+
+    fn intellect(&self) -> f64 {
+        0.0
+    }
+
+    fn spirit(&self) -> f64 {
+        0.0
+    }
+
+    fn spell_power(&self, school: School) -> f64 {
+        0.0
+    }
+
+    fn spell_penetration(&self, school: School) -> f64 {
+        0.0
+    }
+
+    fn set_gcd(&mut self, gcd: f64) {
+        self.t_gcd = gcd;
+    }
+
+    fn auras(&mut self) -> &mut aura::Auras {
+        &mut self.auras
+    }
+
+    fn cooldowns(&mut self) -> &mut cooldown::Cooldowns {
+        &mut self.cooldowns
+    }
+
+    fn on_event(&mut self, event: &Event) -> Vec<Event> {
+        let mut events: Vec<Event> = Vec::new();
+
+        if event.event_type != EventType::CastSuccess || event.spell.is_none() {
+            return events;
+        }
+
+        let player_ids = self.player_ids();
+        if player_ids.is_empty() {
+            return events;
+        }
+
+        self.schedule_index+= 1;
+
+        let spell = event.spell.as_ref().unwrap();
+        match spell.id {
+            // Mana Tide Totem hits the whole party
+            spell::MANA_TIDE => {
+                for player_id in player_ids {
+                    for i in 1..=4 {
+                        events.push(self.player_mana_event(spell, 290.0, player_id, 3.0 * i as f64));
+                    }
+                    events.push(self.player_aura_event(aura::mana_tide(), player_id));
+                }
+            }
+            // Other buffs go to the next player in line
+            id => {
+                let player_id = player_ids[self.player_index % player_ids.len()];
+                self.player_index+= 1;
+
+                if id == spell::INNERVATE {
+                    events.push(self.player_aura_event(aura::innervate(), player_id));
+                } else if id == spell::POWER_INFUSION {
+                    events.push(self.player_aura_event(aura::power_infusion(), player_id));
+                }
+            }
+        }
+
+        events
+    }
+
+    fn next_event(&mut self, t: f64, targets: &HashMap<i32, Target>) -> Event {
+        let spell = match self.support_spell() {
+            Some(spell) if !self.player_ids().is_empty() => spell,
+            // Nothing to do for the rest of the fight
+            _ => return self.wait(f64::MAX),
+        };
+
+        if t < self.t_gcd {
+            return self.wait(self.t_gcd - t);
+        }
+
+        let mut t_next = t;
+        if let Some(cooldown) = self.cooldowns.cooldowns.get(&spell.id) {
+            t_next = t_next.max(cooldown.t_expires);
+        }
+
+        let schedule = &self.support_config().schedule;
+        if !schedule.is_empty() {
+            match schedule.get(self.schedule_index) {
+                Some(t_cast) => t_next = t_next.max(*t_cast),
+                None => return self.wait(f64::MAX),
+            }
+        }
+
+        if t_next > t {
+            return self.wait(t_next - t);
+        }
+
+        let mut event = self.spell_event(spell, 0);
+        event.is_main_event = true;
+        event
+    }
+}
@@ -4,8 +4,10 @@ use std::collections::HashMap;
 pub struct Target {
     pub id: i32,
     pub name: String,
+    pub max_health: f64,
     pub unit_dmg: HashMap<i32, u64>,
     pub unit_ignite_dmg: HashMap<i32, u64>,
+    pub unit_threat: HashMap<i32, f64>,
     pub auras: aura::Auras,
     pub ignite_dmg: f64,
     pub ignite_modifier: f64,
@@ -19,8 +21,10 @@ impl Target {
         Self {
             id,
             name: format!("Target {}", id),
+            max_health: 0.0,
             unit_dmg: HashMap::new(),
             unit_ignite_dmg: HashMap::new(),
+            unit_threat: HashMap::new(),
             auras: Default::default(),
             ignite_dmg: 0.0,
             ignite_modifier: 1.0,
@@ -39,6 +43,16 @@ impl Target {
         self.unit_dmg.values().sum()
     }
 
+    // Targets don't die, so this can go below zero
+    pub fn health(&self) -> f64 {
+        self.max_health - self.total_dmg() as f64
+    }
+
+    // Frozen in place by frost nova or frostbite
+    pub fn is_frozen(&self) -> bool {
+        self.auras.has_any(aura::FROSTBITE) || self.auras.has_any(aura::FROST_NOVA)
+    }
+
     pub fn add_ignite_dmg(&mut self, unit_id: i32, dmg: u64) {
         let total = self.unit_ignite_dmg.entry(unit_id).or_insert(0);
         *total += dmg;
@@ -48,6 +62,11 @@ impl Target {
         self.unit_ignite_dmg.values().sum()
     }
 
+    pub fn add_threat(&mut self, unit_id: i32, threat: f64) {
+        let total = self.unit_threat.entry(unit_id).or_insert(0.0);
+        *total += threat;
+    }
+
     pub fn reset_ignite(&mut self) {
         self.ignite_dmg = 0.0;
         self.ignite_modifier = 1.0;
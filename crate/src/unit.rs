@@ -68,6 +68,14 @@ pub trait Unit {
         0.0
     }
 
+    fn spell_range_mod(&self, spell: &Spell) -> f64 {
+        0.0
+    }
+
+    fn spell_threat_multiplier(&self, spell: &Spell) -> f64 {
+        1.0
+    }
+
     fn spell_hit_chance(&self, spell: &Spell) -> f64 {
         0.0
     }
@@ -76,6 +84,11 @@ pub trait Unit {
         0.0
     }
 
+    // Extra crit chance against frozen targets
+    fn spell_crit_chance_frozen(&self, spell: &Spell) -> f64 {
+        0.0
+    }
+
     fn spell_crit_dmg_base_multiplier(&self, spell: &Spell) -> f64 {
         1.0
     }
@@ -93,6 +106,9 @@ pub trait Unit {
         spell.this_mana_cost = self.spell_mana_cost(&spell);
         spell.coeff+= self.spell_coeff_mod(&spell);
         spell.cooldown+= self.spell_cooldown_mod(&spell);
+        if spell.range > 0.0 {
+            spell.range+= self.spell_range_mod(&spell);
+        }
         spell
     }
 
@@ -181,6 +197,7 @@ pub trait Unit {
         event.is_main_event = false;
         event.unit_id = self.id();
         event.cooldown = Some(cooldown::Cooldown::new(spell.id, spell.name.clone(), spell.cooldown));
+        event.cooldown.as_mut().unwrap().school = spell.school;
 
         event
     }
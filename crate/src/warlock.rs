@@ -0,0 +1,386 @@
+use crate::aura;
+use crate::buff;
+use crate::common;
+use crate::common::School;
+use crate::config::Config;
+use crate::config::PlayerConfig;
+use crate::cooldown;
+use crate::event::Event;
+use crate::event::EventType;
+use crate::spell;
+use crate::stats::Stats;
+use crate::target::Target;
+use crate::unit::Unit;
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+
+pub(crate) const TALENT_SUPPRESSION: usize = 0;
+pub(crate) const TALENT_IMP_CORRUPTION: usize = 1;
+pub(crate) const TALENT_IMP_LIFE_TAP: usize = 4;
+pub(crate) const TALENT_SHADOW_MASTERY: usize = 15;
+pub(crate) const TALENT_IMP_SHADOW_BOLT: usize = 34;
+pub(crate) const TALENT_CATACLYSM: usize = 35;
+pub(crate) const TALENT_BANE: usize = 36;
+pub(crate) const TALENT_DEVASTATION: usize = 40;
+pub(crate) const TALENT_RUIN: usize = 47;
+
+const BASE_MANA: f64 = 1093.0;
+
+#[derive(Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Curse {
+    #[default]
+    None,
+    Agony,
+    Elements,
+    Shadows,
+}
+
+// A warlock with a fixed rotation:
+// Keep the curse and corruption up, life tap when low on mana and cast shadow bolt
+pub struct Warlock {
+    pub id: i32,
+    pub name: String,
+    pub level: i32,
+    pub config: Option<Config>,
+    pub mana: f64,
+    pub t_gcd: f64,
+    pub t_mana_spent: f64,
+    pub stats: Stats,
+    pub auras: aura::Auras,
+    pub cooldowns: cooldown::Cooldowns,
+    buffs: Vec<buff::Buff>,
+}
+
+impl Warlock {
+    pub fn new() -> Self {
+        Self {
+            id: 1,
+            name: String::from("Warlock"),
+            level: 60,
+            config: None,
+            mana: 0.0,
+            t_gcd: 0.0,
+            t_mana_spent: 0.0,
+            stats: Stats::default(),
+            auras: Default::default(),
+            cooldowns: Default::default(),
+            buffs: vec![],
+        }
+    }
+
+    fn player_config(&self) -> &PlayerConfig {
+        &self.config.as_ref().unwrap().players[(self.id as usize) - 1]
+    }
+
+    fn talent(&self, index: usize) -> u8 {
+        *self.player_config().talents.get(index).unwrap_or(&0)
+    }
+
+    fn life_tap_mana(&self) -> f64 {
+        424.0 * (1.0 + 0.1 * self.talent(TALENT_IMP_LIFE_TAP) as f64)
+    }
+
+    // Warlocks stay on the first target
+    fn target_id(&self, targets: &HashMap<i32, Target>) -> i32 {
+        let mut ids: Vec<i32> = targets.keys().copied().collect();
+        ids.sort();
+        *ids.first().unwrap_or(&0)
+    }
+
+    fn curse_spell(&self, target: &Target) -> Option<spell::Spell> {
+        match self.player_config().curse {
+            Curse::Agony if !target.auras.has(spell::CURSE_OF_AGONY, self.id) => Some(spell::curse_of_agony()),
+            Curse::Elements if !target.auras.has_any(aura::CURSE_OF_ELEMENTS) => Some(spell::curse_of_elements()),
+            Curse::Shadows if !target.auras.has_any(aura::CURSE_OF_SHADOWS) => Some(spell::curse_of_shadows()),
+            _ => None,
+        }
+    }
+
+    fn rotation_spell(&self, target: Option<&Target>) -> spell::Spell {
+        if let Some(target) = target {
+            if let Some(spell) = self.curse_spell(target) {
+                return spell;
+            }
+            if !target.auras.has(spell::CORRUPTION, self.id) {
+                return spell::corruption();
+            }
+        }
+
+        if self.mana < self.spell_mana_cost(&spell::shadow_bolt()) {
+            return spell::life_tap();
+        }
+
+        spell::shadow_bolt()
+    }
+}
+
+// Level 60 warlock base stats
+fn race_base_stats(race: common::Race) -> Stats {
+    let (int, spi) = match race {
+        common::Race::Gnome => (118.0, 117.0),
+        common::Race::Human => (111.0, 123.0),
+        common::Race::Orc => (106.0, 122.0),
+        common::Race::Undead => (108.0, 124.0),
+        // Not a warlock race
+        _ => (0.0, 0.0),
+    };
+
+    Stats { int, spi, crit: 1.7, ..Default::default() }
+}
+
+impl Default for Warlock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Unit for Warlock {
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn reset(&mut self) {
+        self.mana = self.max_mana();
+        self.t_gcd = 0.0;
+        self.t_mana_spent = 0.0;
+        self.auras.reset();
+        self.cooldowns.reset();
+    }
+
+    fn set_config(&mut self, config: Config) {
+        self.config = Some(config);
+        self.buffs = buff::active_buffs(&self.player_config().buffs, self.player_config().race);
+        let player = self.player_config();
+        self.stats = buff::player_stats(player, &self.buffs, race_base_stats(player.race), 60.6);
+    }
+
+    // The rotation doesn't roll anything, the sim rolls the spells
+    fn new_rng(&mut self, rng_seed: u64) {}
+
+    fn level(&self) -> i32 {
+        self.level
+    }
+
+    fn max_mana(&self) -> f64 {
+        // Subtract 280 because the first 20 intellect only gives 1 mana instead of 15
+        BASE_MANA + self.stats.int * 15.0 - 280.0 + self.stats.mana
+    }
+
+    fn current_mana(&self) -> f64 {
+        self.mana
+    }
+
+    fn mod_mana(&mut self, mana: f64, t: f64) {
+        self.mana = (self.mana + mana).clamp(0.0, self.max_mana());
+
+        if mana < 0.0 {
+            self.t_mana_spent = t;
+        }
+    }
+
+    fn mana_per_second(&self, t: f64) -> f64 {
+        let mut mps = self.mp5() / 5.0;
+
+        if self.t_mana_spent + 5.0 < t || self.auras.has_any(aura::INNERVATE) {
+            let mut spi = self.spirit_regen();
+            if self.auras.has_any(aura::INNERVATE) {
+                spi*= 5.0;
+            }
+            mps+= spi;
+        }
+
+        mps
+    }
+
+    // 15 + spirit/5 every 2 seconds
+    fn spirit_regen(&self) -> f64 {
+        (15.0 + self.spirit() / 5.0) / 2.0
+    }
+
+    fn mp5(&self) -> f64 {
+        self.stats.mp5 + self.auras.stats.mp5
+    }
+
+    fn intellect(&self) -> f64 {
+        self.stats.int + self.auras.stats.int
+    }
+
+    fn spirit(&self) -> f64 {
+        self.stats.spi + self.auras.stats.spi
+    }
+
+    fn spell_power(&self, school: School) -> f64 {
+        let mut sp = self.stats.sp + self.auras.stats.sp;
+
+        if school == School::Shadow {
+            sp+= self.stats.sp_shadow + self.auras.stats.sp_shadow;
+        }
+
+        sp
+    }
+
+    fn spell_penetration(&self, school: School) -> f64 {
+        self.stats.spell_penetration + self.auras.stats.spell_penetration
+    }
+
+    fn base_cast_time(&self, spell: &spell::Spell) -> f64 {
+        let mut cast_time = spell.cast_time;
+
+        if spell.id == spell::SHADOW_BOLT {
+            cast_time-= 0.1 * self.talent(TALENT_BANE) as f64;
+        }
+        if spell.id == spell::CORRUPTION {
+            cast_time-= 0.4 * self.talent(TALENT_IMP_CORRUPTION) as f64;
+        }
+
+        cast_time.max(0.0)
+    }
+
+    fn spell_mana_cost(&self, spell: &spell::Spell) -> f64 {
+        let mut cost = spell.mana_cost;
+
+        if spell.id == spell::SHADOW_BOLT {
+            cost*= 1.0 - 0.01 * self.talent(TALENT_CATACLYSM) as f64;
+        }
+
+        cost
+    }
+
+    fn spell_hit_chance(&self, spell: &spell::Spell) -> f64 {
+        let mut hit = self.stats.hit + self.auras.stats.hit;
+
+        if spell.id != spell::SHADOW_BOLT {
+            hit+= 2.0 * self.talent(TALENT_SUPPRESSION) as f64;
+        }
+
+        hit
+    }
+
+    fn spell_crit_chance(&self, spell: &spell::Spell) -> f64 {
+        let mut crit = self.stats.crit + self.auras.stats.crit;
+
+        if spell.id == spell::SHADOW_BOLT {
+            crit+= self.talent(TALENT_DEVASTATION) as f64;
+        }
+
+        crit
+    }
+
+    fn spell_crit_dmg_multiplier(&self, spell: &spell::Spell) -> f64 {
+        if spell.id == spell::SHADOW_BOLT && self.talent(TALENT_RUIN) > 0 {
+            return 2.0;
+        }
+
+        1.0
+    }
+
+    fn buff_spell_dmg_multiplier(&self, spell: &spell::Spell) -> f64 {
+        let mut dmg = buff::dmg_multiplier(self.player_config(), &self.buffs);
+
+        if self.auras.has_any(aura::POWER_INFUSION) {
+            dmg*= 1.2;
+        }
+        if spell.school == School::Shadow {
+            dmg*= 1.0 + 0.02 * self.talent(TALENT_SHADOW_MASTERY) as f64;
+        }
+
+        dmg
+    }
+
+    fn set_gcd(&mut self, gcd: f64) {
+        self.t_gcd = gcd;
+    }
+
+    fn auras(&mut self) -> &mut aura::Auras {
+        &mut self.auras
+    }
+
+    fn cooldowns(&mut self) -> &mut cooldown::Cooldowns {
+        &mut self.cooldowns
+    }
+
+    fn on_event(&mut self, event: &Event) -> Vec<Event> {
+        let mut events: Vec<Event> = Vec::new();
+
+        match event.event_type {
+            EventType::CastSuccess => {
+                if let Some(spell) = &event.spell {
+                    match spell.id {
+                        spell::CURSE_OF_ELEMENTS => {
+                            events.push(self.aura_event(aura::curse_of_elements(), event.target_id));
+                        }
+                        spell::CURSE_OF_SHADOWS => {
+                            events.push(self.aura_event(aura::curse_of_shadows(), event.target_id));
+                        }
+                        spell::LIFE_TAP => {
+                            events.push(self.mana_event(self.life_tap_mana(), String::from("Life Tap")));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            EventType::SpellImpact => {
+                if let Some(instance) = &event.spell_instance {
+                    if instance.spell.id == spell::SHADOW_BOLT && instance.result == spell::SpellResult::Crit && self.talent(TALENT_IMP_SHADOW_BOLT) > 0 {
+                        events.push(self.aura_event(aura::shadow_vulnerability(self.talent(TALENT_IMP_SHADOW_BOLT)), event.target_id));
+                    }
+                }
+            }
+
+            _ => {}
+        }
+
+        events
+    }
+
+    fn next_event(&mut self, t: f64, targets: &HashMap<i32, Target>) -> Event {
+        if t < self.t_gcd {
+            let mut event = Event::new(EventType::Wait);
+            event.t = self.t_gcd - t;
+            event.text = String::from("GCD");
+            return event;
+        }
+
+        let target_id = self.target_id(targets);
+        let spell = self.rotation_spell(targets.get(&target_id));
+
+        let mut event = self.spell_event(self.this_spell(spell), target_id);
+        event.is_main_event = true;
+        event
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::Class;
+
+    #[test]
+    fn stats() {
+        let mut player = PlayerConfig {
+            class: Class::Warlock,
+            race: common::Race::Orc,
+            stats: Stats { int: 200.0, ..Default::default() },
+            buffs: vec![buff::Buff::ArcaneIntellect],
+            gear_stats: true,
+            ..Default::default()
+        };
+        let mut warlock = Warlock::new();
+        warlock.set_config(Config { players: vec![player.clone()], ..Default::default() });
+
+        let ai = buff::effect(buff::Buff::ArcaneIntellect).stats.int;
+        assert_eq!(warlock.stats.int, 200.0 + 106.0 + ai);
+        assert!((warlock.stats.crit - 1.7 - warlock.stats.int / 60.6).abs() < 0.000001);
+        assert_eq!(warlock.max_mana(), BASE_MANA + warlock.stats.int * 15.0 - 280.0);
+
+        // Character sheet stats are used as they are
+        player.gear_stats = false;
+        warlock.set_config(Config { players: vec![player], ..Default::default() });
+        assert_eq!(warlock.stats.int, 200.0);
+    }
+}